@@ -0,0 +1,57 @@
+//! Compares generating the terrain in a plain serial loop on the calling thread, which matches the
+//! original single-task generator, with generating it on a task pool using every available core.
+//! The chunk cache is bypassed.
+//!
+//! The resolution defaults to [`DEFAULT_RESOLUTION`] to keep runs short and can be changed with the
+//! `TERRAIN_BENCH_RESOLUTION` environment variable, e.g. to the 5000 of the demo.
+
+use bevy::tasks::{available_parallelism, TaskPool, TaskPoolBuilder};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use terrain::progress::GenerationProgress;
+use terrain::terrain::{generate_terrain_mesh_on, TerrainSettings};
+
+const DEFAULT_RESOLUTION: usize = 1024;
+
+fn generation(c: &mut Criterion) {
+    let resolution = std::env::var("TERRAIN_BENCH_RESOLUTION")
+        .ok()
+        .and_then(|resolution| resolution.parse().ok())
+        .unwrap_or(DEFAULT_RESOLUTION);
+    let settings = TerrainSettings {
+        resolution,
+        ..Default::default()
+    };
+
+    // A scope also runs tasks on the calling thread, so the pool gets one thread fewer than the
+    // number of threads it is reported with.
+    let threads = available_parallelism().max(2);
+    let pool = TaskPoolBuilder::new().num_threads(threads - 1).build();
+
+    let mut group = c.benchmark_group("generation");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(((resolution + 1) * (resolution + 1)) as u64));
+
+    let mut bench = |id: BenchmarkId, pool: Option<&TaskPool>| {
+        group.bench_function(id, |b| {
+            b.iter(|| {
+                black_box(generate_terrain_mesh_on(
+                    pool,
+                    &settings,
+                    None,
+                    &GenerationProgress::default(),
+                ))
+            })
+        });
+    };
+    bench(BenchmarkId::new("serial", resolution), None);
+    bench(
+        BenchmarkId::new(format!("{threads}_threads"), resolution),
+        Some(&pool),
+    );
+
+    group.finish();
+}
+
+criterion_group!(benches, generation);
+criterion_main!(benches);
@@ -0,0 +1,45 @@
+//! Compares evaluating fBm one point at a time with the batched [`fbm_lanes`] path, over rows of
+//! points laid out the way terrain generation samples them.
+
+use bevy::prelude::Vec2;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::hint::black_box;
+use terrain::noise::{fbm, fbm_lanes, Lanes, LANES};
+
+const SEED: u32 = 3266489917;
+const ROW_LEN: usize = 1024;
+
+fn row() -> (Vec<f32>, f32) {
+    let xs = (0..ROW_LEN).map(|col| (col as f32 - 512.0) / 800.0).collect();
+    (xs, 1.37)
+}
+
+fn fbm_row(c: &mut Criterion) {
+    let (xs, y) = row();
+
+    let mut group = c.benchmark_group("fbm_row");
+    group.throughput(Throughput::Elements(ROW_LEN as u64));
+
+    group.bench_function("scalar", |b| {
+        b.iter(|| {
+            for &x in &xs {
+                black_box(fbm(Vec2::new(x, y), black_box(SEED)));
+            }
+        })
+    });
+
+    group.bench_function("lanes", |b| {
+        let ys = [y; LANES];
+        b.iter(|| {
+            for batch in xs.chunks_exact(LANES) {
+                let batch: &Lanes = batch.try_into().unwrap();
+                black_box(fbm_lanes(batch, &ys, black_box(SEED)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, fbm_row);
+criterion_main!(benches);
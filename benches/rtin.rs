@@ -0,0 +1,45 @@
+//! Measures simplifying one terrain chunk with [`Rtin`], next to sampling the same chunk, so that
+//! the cost of the per-triangle deviation scan can be read against the rest of chunk generation.
+//!
+//! The scan visits every grid point under each triangle the error hierarchy alone would accept,
+//! which adds up to about `n log n` points for a chunk of `n` vertices.
+
+use bevy::prelude::IVec2;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::hint::black_box;
+use terrain::rtin::Rtin;
+use terrain::terrain::{generate_chunk_data, TerrainSettings, CHUNK_SIZE, SIMPLIFIED_MAX_ERROR};
+
+fn chunk(c: &mut Criterion) {
+    let settings = TerrainSettings::default();
+    let coord = IVec2::new(3, 7);
+    let grid_size = CHUNK_SIZE + 1;
+    let heights = generate_chunk_data(&settings, coord).heights;
+    let rtin = Rtin::new(&heights, grid_size);
+
+    let mut group = c.benchmark_group("chunk");
+    group.throughput(Throughput::Elements((grid_size * grid_size) as u64));
+
+    group.bench_function("sample", |b| {
+        b.iter(|| black_box(generate_chunk_data(&settings, black_box(coord))))
+    });
+
+    group.bench_function("rtin_errors", |b| {
+        b.iter(|| black_box(Rtin::new(black_box(&heights), grid_size)))
+    });
+
+    group.bench_function("rtin_triangulate", |b| {
+        b.iter(|| {
+            black_box(rtin.triangulate_chunk(
+                black_box(SIMPLIFIED_MAX_ERROR),
+                CHUNK_SIZE,
+                CHUNK_SIZE,
+            ))
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, chunk);
+criterion_main!(benches);
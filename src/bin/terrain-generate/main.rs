@@ -0,0 +1,253 @@
+//! Generates a terrain without opening a window and writes its heightmap, normal map, splat map
+//! and mesh to disk.
+//!
+//! Builds without the `demo` feature, so it runs on machines without display, audio or gamepad
+//! libraries.
+
+use bevy::prelude::*;
+use bevy::tasks::{available_parallelism, TaskPoolBuilder};
+use image::{ImageBuffer, Luma, Rgb};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use terrain::progress::GenerationProgress;
+use terrain::terrain::{generate_terrain_mesh_on, GeneratedTerrain, TerrainSettings};
+
+/// Directory, relative to the working directory, the outputs are written to unless `--out` names
+/// another.
+const DEFAULT_OUTPUT_DIR: &str = "terrain_export";
+
+const USAGE: &str = "Usage: terrain-generate [--settings <settings.ron>] [--seed <seed>] \
+[--size <resolution>] [--out <dir>] [--simplified]";
+
+/// Command line options.
+struct GenerateOptions {
+    settings: TerrainSettings,
+    output_dir: PathBuf,
+    simplified: bool,
+}
+
+impl GenerateOptions {
+    /// Parses the arguments following the program name. `--seed` and `--size` override the settings file.
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut settings_path = None;
+        let mut seed = None;
+        let mut resolution = None;
+        let mut output_dir = PathBuf::from(DEFAULT_OUTPUT_DIR);
+        let mut simplified = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--settings" => settings_path = Some(PathBuf::from(value()?)),
+                "--seed" => seed = Some(parse_number(arg, value()?)?),
+                "--size" => resolution = Some(parse_number(arg, value()?)?),
+                "--out" => output_dir = PathBuf::from(value()?),
+                "--simplified" => simplified = true,
+                _ => return Err(format!("Unknown argument {arg}")),
+            }
+        }
+
+        let mut settings = match settings_path {
+            Some(path) => load_settings(&path)
+                .map_err(|error| format!("Failed to load {}: {error}", path.display()))?,
+            None => TerrainSettings::default(),
+        };
+        if let Some(seed) = seed {
+            settings.seed = seed;
+        }
+        if let Some(resolution) = resolution {
+            settings.resolution = resolution;
+        }
+
+        if settings.resolution == 0 {
+            return Err("The size must be positive".to_string());
+        }
+
+        Ok(Self {
+            settings,
+            output_dir,
+            simplified,
+        })
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{name} expects a number, got {value}"))
+}
+
+fn load_settings(path: &Path) -> io::Result<TerrainSettings> {
+    let text = fs::read_to_string(path)?;
+    ron::from_str(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Description of the outputs, written next to them so that heights can be restored from the
+/// normalised heightmap.
+#[derive(Serialize)]
+struct ExportInfo {
+    settings: TerrainSettings,
+    /// World-space x and z of the first pixel and vertex.
+    origin: [f64; 2],
+    /// Heights mapped to black and white in the heightmap.
+    min_height: f32,
+    max_height: f32,
+    mesh: &'static str,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match GenerateOptions::parse(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    if let Err(error) = generate(&options) {
+        eprintln!("Failed to export terrain: {error}");
+        std::process::exit(1);
+    }
+}
+
+fn generate(options: &GenerateOptions) -> io::Result<()> {
+    let settings = &options.settings;
+    println!(
+        "Generating {0}x{0} terrain with seed {1}",
+        settings.resolution, settings.seed
+    );
+
+    let pool = TaskPoolBuilder::new()
+        .num_threads(available_parallelism())
+        .build();
+    let start = Instant::now();
+    let terrain =
+        generate_terrain_mesh_on(Some(&pool), settings, None, &GenerationProgress::default());
+    println!("  generated in {:.2?}", start.elapsed());
+
+    let dir = &options.output_dir;
+    fs::create_dir_all(dir)?;
+
+    let (min_height, max_height) = write_heightmap(&terrain, &dir.join("heightmap.png"))?;
+    write_normal_map(&terrain, &dir.join("normals.png"))?;
+    write_splat_map(&terrain, &dir.join("splat.png"))?;
+    write_obj(&terrain, options.simplified, &dir.join("terrain.obj"))?;
+
+    let origin = terrain.heightfield.origin();
+    let info = ExportInfo {
+        settings: settings.clone(),
+        origin: [origin.x, origin.y],
+        min_height,
+        max_height,
+        mesh: if options.simplified {
+            "simplified"
+        } else {
+            "full"
+        },
+    };
+    let text = ron::ser::to_string_pretty(&info, ron::ser::PrettyConfig::default())
+        .map_err(io::Error::other)?;
+    fs::write(dir.join("terrain.ron"), text)?;
+
+    println!("  written to {} in {:.2?}", dir.display(), start.elapsed());
+    Ok(())
+}
+
+/// Side length in pixels of the maps, one pixel per grid vertex. Pixel x runs along world x and
+/// pixel y along world z.
+fn map_size(terrain: &GeneratedTerrain) -> u32 {
+    terrain.heightfield.resolution() as u32 + 1
+}
+
+fn grid_index(terrain: &GeneratedTerrain, x: u32, y: u32) -> usize {
+    x as usize * map_size(terrain) as usize + y as usize
+}
+
+/// Writes heights as a 16-bit greyscale PNG, normalised to the height range, which is returned.
+fn write_heightmap(terrain: &GeneratedTerrain, path: &Path) -> io::Result<(f32, f32)> {
+    let heights = terrain.heightfield.heights();
+    let min_height = heights.iter().copied().fold(f32::MAX, f32::min);
+    let max_height = heights.iter().copied().fold(f32::MIN, f32::max);
+    let range = (max_height - min_height).max(f32::EPSILON);
+
+    let size = map_size(terrain);
+    let image = ImageBuffer::from_fn(size, size, |x, y| {
+        let height = heights[grid_index(terrain, x, y)];
+        Luma([((height - min_height) / range * u16::MAX as f32).round() as u16])
+    });
+    image.save(path).map_err(io::Error::other)?;
+
+    Ok((min_height, max_height))
+}
+
+/// Writes world-space normals as RGB, mapping each component from `[-1, 1]` to `[0, 255]`.
+fn write_normal_map(terrain: &GeneratedTerrain, path: &Path) -> io::Result<()> {
+    let size = map_size(terrain);
+    let image = ImageBuffer::from_fn(size, size, |x, y| {
+        let normal = terrain.normals[grid_index(terrain, x, y)];
+        Rgb(normal.map(|component| ((component * 0.5 + 0.5) * 255.0).round() as u8))
+    });
+    image.save(path).map_err(io::Error::other)
+}
+
+/// Writes the snow cover to the red channel and the tree cover to the green channel.
+fn write_splat_map(terrain: &GeneratedTerrain, path: &Path) -> io::Result<()> {
+    let size = map_size(terrain);
+    let image = ImageBuffer::from_fn(size, size, |x, y| {
+        let [snow, tree] = terrain.splat[grid_index(terrain, x, y)];
+        Rgb([
+            (snow * 255.0).round() as u8,
+            (tree * 255.0).round() as u8,
+            0,
+        ])
+    });
+    image.save(path).map_err(io::Error::other)
+}
+
+/// Writes the chunk meshes as a single Wavefront OBJ in world space.
+fn write_obj(terrain: &GeneratedTerrain, simplified: bool, path: &Path) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "# {} terrain chunks", terrain.chunks.len())?;
+
+    let mut first_vertex = 1;
+    for chunk in &terrain.chunks {
+        let mesh = if simplified {
+            &chunk.simplified_mesh
+        } else {
+            &chunk.mesh
+        };
+        let (Some(positions), Some(normals), Some(indices)) = (
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+                .and_then(|positions| positions.as_float3()),
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+                .and_then(|normals| normals.as_float3()),
+            mesh.indices(),
+        ) else {
+            continue;
+        };
+
+        for [x, y, z] in positions {
+            let x = chunk.origin.x + *x as f64;
+            let z = chunk.origin.z + *z as f64;
+            writeln!(file, "v {x} {y} {z}")?;
+        }
+        for [x, y, z] in normals {
+            writeln!(file, "vn {x} {y} {z}")?;
+        }
+
+        let indices: Vec<usize> = indices.iter().map(|index| index + first_vertex).collect();
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]];
+            writeln!(file, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+
+        first_vertex += positions.len();
+    }
+
+    file.flush()
+}
@@ -0,0 +1,205 @@
+use crate::flythrough::{Flythrough, FlythroughPath};
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use terrain::camera::MainCamera;
+use terrain::culling::TerrainCullingStats;
+use terrain::origin::WorldOrigin;
+use terrain::terrain::TerrainSettings;
+
+/// File, relative to the working directory, the report is written to unless `--benchmark-report`
+/// names another.
+pub const DEFAULT_REPORT_PATH: &str = "benchmark.json";
+
+/// Frames rendered at the start of the route before timing begins, so that pipeline compilation
+/// and the first shadow cascades do not end up in the results.
+const WARMUP_FRAMES: u32 = 120;
+
+/// A benchmark run, flying a recorded route over the default seed and timing every frame.
+///
+/// The chunk cache is bypassed, so the generation time always covers a full generation.
+#[derive(Resource)]
+pub struct Benchmark {
+    route: PathBuf,
+    report: PathBuf,
+    generation_started: Option<Instant>,
+    generation_time: Duration,
+    warmup_frames: u32,
+    /// Frame times in milliseconds.
+    frame_times: Vec<f64>,
+    visible_triangles: Vec<usize>,
+    total_triangles: usize,
+}
+
+impl Benchmark {
+    pub fn new(route: PathBuf, report: PathBuf) -> Self {
+        Self {
+            route,
+            report,
+            generation_started: None,
+            generation_time: Duration::ZERO,
+            warmup_frames: 0,
+            frame_times: Vec::new(),
+            visible_triangles: Vec::new(),
+            total_triangles: 0,
+        }
+    }
+
+    fn report(&self, settings: &TerrainSettings, path: &FlythroughPath) -> BenchmarkReport {
+        let mut frame_times = self.frame_times.clone();
+        frame_times.sort_by(f64::total_cmp);
+
+        let visible_avg = if self.visible_triangles.is_empty() {
+            0.0
+        } else {
+            self.visible_triangles.iter().sum::<usize>() as f64 / self.visible_triangles.len() as f64
+        };
+
+        BenchmarkReport {
+            seed: settings.seed,
+            resolution: settings.resolution,
+            route: self.route.display().to_string(),
+            route_duration_seconds: path.duration(),
+            frames: frame_times.len(),
+            generation_time_ms: self.generation_time.as_secs_f64() * 1000.0,
+            frame_time_ms: FrameTimeStats {
+                min: frame_times.first().copied().unwrap_or(0.0),
+                avg: frame_times.iter().sum::<f64>() / frame_times.len().max(1) as f64,
+                p95: percentile(&frame_times, 0.95),
+                p99: percentile(&frame_times, 0.99),
+                max: frame_times.last().copied().unwrap_or(0.0),
+            },
+            triangles: TriangleStats {
+                total: self.total_triangles,
+                visible_avg,
+                visible_max: self.visible_triangles.iter().copied().max().unwrap_or(0),
+            },
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[f64], fraction: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Serialize)]
+struct BenchmarkReport {
+    seed: u32,
+    resolution: usize,
+    route: String,
+    route_duration_seconds: f32,
+    frames: usize,
+    /// Time from the start of generation until the terrain was uploaded and the route could start.
+    generation_time_ms: f64,
+    frame_time_ms: FrameTimeStats,
+    triangles: TriangleStats,
+}
+
+#[derive(Serialize)]
+struct FrameTimeStats {
+    min: f64,
+    avg: f64,
+    p95: f64,
+    p99: f64,
+    max: f64,
+}
+
+#[derive(Serialize)]
+struct TriangleStats {
+    total: usize,
+    visible_avg: f64,
+    visible_max: usize,
+}
+
+/// Run condition for the systems that are skipped while benchmarking, such as grabbing the cursor
+/// and reacting to gameplay input.
+pub fn not_benchmarking(benchmark: Option<Res<Benchmark>>) -> bool {
+    benchmark.is_none()
+}
+
+/// Starts timing generation, which the [`TerrainPlugin`](terrain::TerrainPlugin) begins on the
+/// first update.
+pub fn start_generation_timer(mut benchmark: ResMut<Benchmark>) {
+    benchmark.generation_started = Some(Instant::now());
+}
+
+pub fn stop_generation_timer(mut benchmark: ResMut<Benchmark>) {
+    if let Some(started) = benchmark.generation_started {
+        benchmark.generation_time = started.elapsed();
+    }
+}
+
+/// Holds the camera at the start of the route while warming up, then records every frame of the
+/// flythrough and writes the report once it is over.
+#[allow(clippy::too_many_arguments)]
+pub fn benchmark_frame_system(
+    mut benchmark: ResMut<Benchmark>,
+    mut flythrough: ResMut<Flythrough>,
+    path: Res<FlythroughPath>,
+    settings: Res<TerrainSettings>,
+    world_origin: Res<WorldOrigin>,
+    diagnostics: Res<DiagnosticsStore>,
+    culling_stats: Res<TerrainCullingStats>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if benchmark.warmup_frames < WARMUP_FRAMES {
+        benchmark.warmup_frames += 1;
+
+        if let (Ok(mut transform), Some((position, rotation))) =
+            (camera_query.single_mut(), path.sample(0.0))
+        {
+            transform.translation = world_origin.to_render(position);
+            transform.rotation = rotation;
+        }
+
+        if benchmark.warmup_frames == WARMUP_FRAMES {
+            flythrough.playing = Some(0.0);
+        }
+        return;
+    }
+
+    if flythrough.playing.is_some() {
+        if let Some(frame_time) = diagnostics
+            .get(&FrameTimeDiagnosticsPlugin::FRAME_TIME)
+            .and_then(|frame_time| frame_time.value())
+        {
+            benchmark.frame_times.push(frame_time);
+        }
+        benchmark.visible_triangles.push(culling_stats.visible_triangles);
+        benchmark.total_triangles = culling_stats.total_triangles;
+        return;
+    }
+
+    let report = benchmark.report(&settings, &path);
+    let exit_code = match serde_json::to_string_pretty(&report)
+        .map_err(std::io::Error::other)
+        .and_then(|json| fs::write(&benchmark.report, json))
+    {
+        Ok(()) => {
+            info!(
+                "Benchmark finished: {} frames, avg {:.2} ms, p99 {:.2} ms. Report written to {}",
+                report.frames,
+                report.frame_time_ms.avg,
+                report.frame_time_ms.p99,
+                benchmark.report.display()
+            );
+            AppExit::Success
+        }
+        Err(error) => {
+            error!("Failed to write {}: {error}", benchmark.report.display());
+            AppExit::error()
+        }
+    };
+
+    exit.write(exit_code);
+}
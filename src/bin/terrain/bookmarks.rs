@@ -0,0 +1,177 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, CursorOptions};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use terrain::camera::{CameraController, CameraMode, MainCamera};
+use terrain::input::{ActionInput, InputAction};
+use terrain::origin::WorldOrigin;
+use terrain::terrain::TerrainSettings;
+
+/// Directory, relative to the working directory, holding one bookmark file per world seed.
+pub const DEFAULT_BOOKMARKS_DIR: &str = "bookmarks";
+
+/// Time taken to fly to a recalled bookmark.
+const FLIGHT_DURATION: f32 = 1.5;
+
+/// A saved camera pose, in the same terms as the HUD.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// World-space camera position.
+    pub position: [f64; 3],
+    pub pitch_degrees: f32,
+    pub heading_degrees: f32,
+}
+
+impl Bookmark {
+    pub fn from_transform(transform: &Transform, world_origin: &WorldOrigin) -> Self {
+        let forward = transform.forward();
+        let heading = forward.x.atan2(-forward.z).to_degrees();
+
+        Self {
+            position: world_origin.to_world(transform.translation).to_array(),
+            pitch_degrees: forward.y.asin().to_degrees(),
+            heading_degrees: heading.rem_euclid(360.0),
+        }
+    }
+
+    pub fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(-self.heading_degrees.to_radians())
+            * Quat::from_rotation_x(self.pitch_degrees.to_radians())
+    }
+}
+
+/// Numbered camera bookmarks of the current world, recalled with [`InputAction::BOOKMARK_SLOTS`]
+/// and saved by pressing a slot while holding [`InputAction::SaveBookmark`].
+#[derive(Resource)]
+pub struct Bookmarks {
+    seed: u32,
+    slots: BTreeMap<u8, Bookmark>,
+}
+
+impl Bookmarks {
+    fn path(seed: u32) -> PathBuf {
+        PathBuf::from(DEFAULT_BOOKMARKS_DIR).join(format!("{seed}.ron"))
+    }
+
+    /// Loads the bookmarks of a world, starting empty if it has none yet.
+    pub fn load(seed: u32) -> io::Result<Self> {
+        let slots = match fs::read_to_string(Self::path(seed)) {
+            Ok(text) => ron::from_str(&text)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(error),
+        };
+
+        Ok(Self { seed, slots })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(&self.slots, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+
+        fs::create_dir_all(DEFAULT_BOOKMARKS_DIR)?;
+        fs::write(Self::path(self.seed), text)
+    }
+}
+
+pub fn load_bookmarks(mut commands: Commands, settings: Res<TerrainSettings>) {
+    let bookmarks = Bookmarks::load(settings.seed).unwrap_or_else(|error| {
+        warn!("Failed to load bookmarks for seed {}: {error}", settings.seed);
+        Bookmarks {
+            seed: settings.seed,
+            slots: BTreeMap::new(),
+        }
+    });
+
+    commands.insert_resource(bookmarks);
+}
+
+/// Camera flight towards a recalled bookmark.
+#[derive(Component)]
+pub struct BookmarkFlight {
+    from_position: DVec3,
+    from_rotation: Quat,
+    to: Bookmark,
+    elapsed: f32,
+}
+
+/// Saves the camera pose to a slot, or starts flying to the pose saved in it.
+pub fn bookmark_keys_system(
+    mut commands: Commands,
+    input: ActionInput,
+    mut bookmarks: ResMut<Bookmarks>,
+    world_origin: Res<WorldOrigin>,
+    camera_query: Query<(Entity, &Transform), With<MainCamera>>,
+) {
+    let Some(slot) = InputAction::BOOKMARK_SLOTS
+        .iter()
+        .position(|action| input.just_pressed(*action))
+    else {
+        return;
+    };
+    let slot = slot as u8 + 1;
+
+    let Ok((entity, transform)) = camera_query.single() else {
+        return;
+    };
+
+    if input.pressed(InputAction::SaveBookmark) {
+        let bookmark = Bookmark::from_transform(transform, &world_origin);
+        bookmarks.slots.insert(slot, bookmark);
+        match bookmarks.save() {
+            Ok(()) => info!("Saved bookmark {slot}: {bookmark:?}"),
+            Err(error) => warn!("Failed to save bookmark {slot}: {error}"),
+        }
+    } else if let Some(bookmark) = bookmarks.slots.get(&slot) {
+        commands.entity(entity).insert(BookmarkFlight {
+            from_position: world_origin.to_world(transform.translation),
+            from_rotation: transform.rotation,
+            to: *bookmark,
+            elapsed: 0.0,
+        });
+    }
+}
+
+/// Moves the camera along its [`BookmarkFlight`], easing in and out.
+pub fn bookmark_flight_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    world_origin: Res<WorldOrigin>,
+    mut camera_query: Query<(
+        Entity,
+        &mut Transform,
+        &mut CameraController,
+        &mut BookmarkFlight,
+    )>,
+    mut cursor_options: Single<&mut CursorOptions>,
+) {
+    let Ok((entity, mut transform, mut controller, mut flight)) = camera_query.single_mut() else {
+        return;
+    };
+
+    flight.elapsed += time.delta_secs();
+    let t = (flight.elapsed / FLIGHT_DURATION).min(1.0);
+    let eased = t * t * (3.0 - 2.0 * t);
+
+    let to_position = DVec3::from_array(flight.to.position);
+    let position = flight.from_position.lerp(to_position, eased as f64);
+    transform.translation = world_origin.to_render(position);
+    transform.rotation = flight.from_rotation.slerp(flight.to.rotation(), eased);
+
+    // Bookmarks are free viewpoints, so arrive flying and at rest. Leaving orbit mode captures
+    // the cursor again, as toggling out of it does.
+    if controller.mode == CameraMode::Orbit {
+        cursor_options.visible = false;
+        cursor_options.grab_mode = CursorGrabMode::Locked;
+    }
+    controller.mode = CameraMode::Fly;
+    controller.velocity = Vec3::ZERO;
+
+    if t >= 1.0 {
+        commands.entity(entity).remove::<BookmarkFlight>();
+    }
+}
@@ -3,6 +3,7 @@ use bevy::prelude::*;
 use bevy::render::render_resource::{
     Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
 };
+use terrain::camera::MainCamera;
 
 pub struct CameraWidgetPlugin;
 
@@ -12,9 +13,6 @@ impl Plugin for CameraWidgetPlugin {
     }
 }
 
-#[derive(Component)]
-pub struct MainCamera;
-
 #[derive(Component)]
 struct WidgetAxes;
 
@@ -0,0 +1,103 @@
+//! Command line options of the interactive demo. Headless export is a separate program,
+//! `terrain-generate`.
+//!
+//! Invalid options print the usage and exit, since there is nothing to run without them.
+
+use crate::benchmark::{self, Benchmark};
+use crate::flythrough::FlythroughPath;
+use crate::screenshot::{CaptureRequest, CommandLineCapture, ScreenshotInfo};
+use bevy::prelude::*;
+
+/// Reads the `--benchmark <route.ron>` option and the route it names.
+pub fn benchmark_args(args: &[String]) -> Option<(Benchmark, FlythroughPath)> {
+    let index = args.iter().position(|arg| arg == "--benchmark")?;
+    let Some(route) = args.get(index + 1) else {
+        eprintln!("Usage: terrain --benchmark <route.ron> [--benchmark-report <report.json>]");
+        std::process::exit(2);
+    };
+    let report = args
+        .iter()
+        .position(|arg| arg == "--benchmark-report")
+        .and_then(|index| args.get(index + 1))
+        .map_or(benchmark::DEFAULT_REPORT_PATH, String::as_str);
+
+    let path = match FlythroughPath::load(route) {
+        Ok(path) if path.keyframes.len() >= 2 => path,
+        Ok(_) => {
+            eprintln!("{route} needs at least two keyframes");
+            std::process::exit(1);
+        }
+        Err(error) => {
+            eprintln!("Failed to load {route}: {error}");
+            std::process::exit(1);
+        }
+    };
+
+    Some((Benchmark::new(route.into(), report.into()), path))
+}
+
+/// Reads the `--screenshot <image.png>` option and the options of the capture.
+pub fn capture_args(args: &[String]) -> Option<CommandLineCapture> {
+    let index = args.iter().position(|arg| arg == "--screenshot")?;
+    match parse_capture_args(args, index) {
+        Ok(capture) => Some(capture),
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!(
+                "Usage: terrain --screenshot <image.png> [--screenshot-size <width>x<height>] \
+                 [--supersample <factor>] [--camera <screenshot.ron>]"
+            );
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Parses the options of a `--screenshot` capture, the path being the argument at `index + 1`.
+fn parse_capture_args(args: &[String], index: usize) -> Result<CommandLineCapture, String> {
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .map(|index| args.get(index + 1).ok_or(format!("{name} needs a value")))
+            .transpose()
+    };
+
+    let path = args.get(index + 1).ok_or("--screenshot needs a path")?;
+    let source = match option("--camera")? {
+        Some(path) => {
+            let source = ScreenshotInfo::load(path)
+                .map_err(|error| format!("Failed to load {path}: {error}"))?;
+            if source.sun_rotation.is_none() {
+                eprintln!("{path} does not record the lighting, using the start lighting instead");
+            }
+            Some(source)
+        }
+        None => None,
+    };
+
+    // Size and supersampling default to those of the reproduced capture.
+    let size = match option("--screenshot-size")? {
+        Some(size) => size
+            .split_once('x')
+            .and_then(|(width, height)| Some(UVec2::new(width.parse().ok()?, height.parse().ok()?)))
+            .filter(|size| size.min_element() > 0)
+            .ok_or(format!("Invalid screenshot size {size}"))?,
+        None => source
+            .as_ref()
+            .map_or(UVec2::new(1920, 1080), |source| UVec2::from_array(source.size)),
+    };
+    let supersample = match option("--supersample")? {
+        Some(factor) => factor
+            .parse()
+            .ok()
+            .filter(|factor| *factor > 0)
+            .ok_or(format!("Invalid supersampling factor {factor}"))?,
+        None => source.as_ref().map_or(1, |source| source.supersample),
+    };
+
+    let request = CaptureRequest {
+        path: path.into(),
+        size,
+        supersample,
+    };
+    Ok(CommandLineCapture::new(request, source))
+}
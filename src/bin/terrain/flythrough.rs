@@ -0,0 +1,339 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+use terrain::camera::{CameraController, CameraMode, MainCamera};
+use terrain::input::{ActionInput, InputAction};
+use terrain::origin::WorldOrigin;
+
+/// File, relative to the working directory, the flythrough path is recorded to.
+pub const DEFAULT_PATH_FILE: &str = "flythrough.ron";
+
+/// Time between the last keyframe of a path and the first keyframe of a recording appended to it.
+const APPEND_GAP: f32 = 2.0;
+
+/// A camera pose at a point in time along a [`FlythroughPath`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// World-space camera position.
+    pub position: [f64; 3],
+    pub rotation: [f32; 4],
+    /// Seconds since the start of the path.
+    pub time: f32,
+}
+
+/// Camera path through a series of keyframes, followed along a centripetal Catmull-Rom spline.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlythroughPath {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl FlythroughPath {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Returns the world-space position and the rotation of the camera at `time`, clamped to the
+    /// ends of the path, or `None` if the path has no keyframes.
+    pub fn sample(&self, time: f32) -> Option<(DVec3, Quat)> {
+        let keyframes = &self.keyframes;
+        let first = keyframes.first()?;
+        if keyframes.len() == 1 || time <= first.time {
+            return Some((DVec3::from_array(first.position), Quat::from_array(first.rotation)));
+        }
+
+        let segment = keyframes
+            .windows(2)
+            .position(|pair| time < pair[1].time)
+            .unwrap_or(keyframes.len() - 2);
+        let (from, to) = (&keyframes[segment], &keyframes[segment + 1]);
+        let span = to.time - from.time;
+        let u = if span > 0.0 {
+            ((time - from.time) / span).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let p1 = DVec3::from_array(from.position);
+        let p2 = DVec3::from_array(to.position);
+        // Extrapolate missing neighbours at the ends of the path.
+        let p0 = match segment.checked_sub(1) {
+            Some(previous) => DVec3::from_array(keyframes[previous].position),
+            None => 2.0 * p1 - p2,
+        };
+        let p3 = match keyframes.get(segment + 2) {
+            Some(next) => DVec3::from_array(next.position),
+            None => 2.0 * p2 - p1,
+        };
+
+        let position = centripetal_catmull_rom(p0, p1, p2, p3, u as f64);
+
+        // Ease the turn in and out of every keyframe.
+        let eased = u * u * (3.0 - 2.0 * u);
+        let rotation =
+            Quat::from_array(from.rotation).slerp(Quat::from_array(to.rotation), eased);
+
+        Some((position, rotation))
+    }
+}
+
+/// Evaluates the centripetal Catmull-Rom segment between `p1` and `p2` at `u` in `[0, 1]`.
+///
+/// Knots are spaced by the square root of the distance between points, which avoids the cusps and
+/// self-intersections of the uniform spline when keyframes are unevenly spaced.
+fn centripetal_catmull_rom(p0: DVec3, p1: DVec3, p2: DVec3, p3: DVec3, u: f64) -> DVec3 {
+    let knot = |a: DVec3, b: DVec3| a.distance(b).sqrt().max(1e-6);
+    let t0 = 0.0;
+    let t1 = t0 + knot(p0, p1);
+    let t2 = t1 + knot(p1, p2);
+    let t3 = t2 + knot(p2, p3);
+    let t = t1 + (t2 - t1) * u;
+
+    let lerp = |a: DVec3, b: DVec3, ta: f64, tb: f64| a + (b - a) * ((t - ta) / (tb - ta));
+
+    // Barry and Goldman's pyramidal formulation
+    let a1 = lerp(p0, p1, t0, t1);
+    let a2 = lerp(p1, p2, t1, t2);
+    let a3 = lerp(p2, p3, t2, t3);
+    let b1 = lerp(a1, a2, t0, t2);
+    let b2 = lerp(a2, a3, t1, t3);
+    lerp(b1, b2, t1, t2)
+}
+
+/// Loads the path from [`DEFAULT_PATH_FILE`], starting with an empty path if there is none.
+pub fn load_flythrough_path() -> FlythroughPath {
+    match FlythroughPath::load(DEFAULT_PATH_FILE) {
+        Ok(path) => path,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => FlythroughPath::default(),
+        Err(error) => {
+            warn!("Failed to load {DEFAULT_PATH_FILE}: {error}");
+            FlythroughPath::default()
+        }
+    }
+}
+
+/// Playback and recording state of the flythrough.
+#[derive(Resource, Default)]
+pub struct Flythrough {
+    /// Time along the path while playing.
+    pub playing: Option<f32>,
+    recording: Option<Recording>,
+}
+
+/// A run of keyframes being appended to the path.
+#[derive(Clone, Copy)]
+struct Recording {
+    /// When the first keyframe of the recording was taken.
+    started: Instant,
+    /// Path time of the first keyframe of the recording.
+    start_time: f32,
+}
+
+impl Flythrough {
+    /// Appends a keyframe taken at `now` to the path, starting a recording if none is running.
+    ///
+    /// A new recording continues after the last keyframe of the path, so recording after loading
+    /// a saved path extends it rather than replacing it.
+    fn record_keyframe(
+        &mut self,
+        path: &mut FlythroughPath,
+        position: DVec3,
+        rotation: Quat,
+        now: Instant,
+    ) {
+        let recording = *self.recording.get_or_insert_with(|| Recording {
+            started: now,
+            start_time: match path.keyframes.last() {
+                Some(last) => last.time + APPEND_GAP,
+                None => 0.0,
+            },
+        });
+
+        path.keyframes.push(Keyframe {
+            position: position.to_array(),
+            rotation: rotation.to_array(),
+            time: recording.start_time + (now - recording.started).as_secs_f32(),
+        });
+    }
+
+    /// Ends the current recording, returning whether one was running.
+    fn stop_recording(&mut self) -> bool {
+        self.recording.take().is_some()
+    }
+
+    /// Removes every keyframe from the path, ending the current recording.
+    fn clear(&mut self, path: &mut FlythroughPath) {
+        path.keyframes.clear();
+        self.recording = None;
+    }
+}
+
+/// Records keyframes from the [`MainCamera`], clears and saves the path, and starts or stops
+/// playback.
+///
+/// Keyframes and clearing only change the path in memory; it is written to [`DEFAULT_PATH_FILE`]
+/// when [`InputAction::SavePath`] ends the recording.
+pub fn flythrough_keys_system(
+    input: ActionInput,
+    mut path: ResMut<FlythroughPath>,
+    mut flythrough: ResMut<Flythrough>,
+    world_origin: Res<WorldOrigin>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+) {
+    if input.just_pressed(InputAction::RecordKeyframe)
+        && let Ok(transform) = camera_query.single()
+    {
+        flythrough.record_keyframe(
+            &mut path,
+            world_origin.to_world(transform.translation),
+            transform.rotation,
+            Instant::now(),
+        );
+        info!("Recorded flythrough keyframe {}", path.keyframes.len());
+    }
+
+    if input.just_pressed(InputAction::ClearPath) {
+        flythrough.clear(&mut path);
+        info!("Cleared flythrough path, save it to keep the change");
+    }
+
+    if input.just_pressed(InputAction::SavePath) {
+        flythrough.stop_recording();
+        if let Err(error) = path.save(DEFAULT_PATH_FILE) {
+            warn!("Failed to save {DEFAULT_PATH_FILE}: {error}");
+        } else {
+            info!(
+                "Saved {} flythrough keyframes to {DEFAULT_PATH_FILE}",
+                path.keyframes.len()
+            );
+        }
+    }
+
+    if input.just_pressed(InputAction::PlayPath) {
+        if flythrough.stop_recording() {
+            info!("Stopped recording, the new keyframes are not saved yet");
+        }
+        flythrough.playing = match flythrough.playing {
+            Some(_) => None,
+            None if path.keyframes.len() >= 2 => Some(0.0),
+            None => {
+                warn!("A flythrough needs at least two keyframes");
+                None
+            }
+        };
+    }
+}
+
+/// Moves the [`MainCamera`] along the path while the flythrough is playing.
+pub fn flythrough_playback_system(
+    time: Res<Time>,
+    path: Res<FlythroughPath>,
+    mut flythrough: ResMut<Flythrough>,
+    world_origin: Res<WorldOrigin>,
+    mut camera_query: Query<(&mut Transform, &mut CameraController), With<MainCamera>>,
+) {
+    let Some(elapsed) = flythrough.playing.as_mut() else {
+        return;
+    };
+    let Ok((mut transform, mut controller)) = camera_query.single_mut() else {
+        return;
+    };
+
+    *elapsed += time.delta_secs();
+    let Some((position, rotation)) = path.sample(*elapsed) else {
+        flythrough.playing = None;
+        return;
+    };
+
+    transform.translation = world_origin.to_render(position);
+    transform.rotation = rotation;
+    controller.mode = CameraMode::Fly;
+    controller.velocity = Vec3::ZERO;
+
+    if *elapsed >= path.duration() {
+        flythrough.playing = None;
+        info!("Flythrough finished after {:.1}s", path.duration());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn record_at(flythrough: &mut Flythrough, path: &mut FlythroughPath, now: Instant) {
+        flythrough.record_keyframe(path, DVec3::ZERO, Quat::IDENTITY, now);
+    }
+
+    fn times(path: &FlythroughPath) -> Vec<f32> {
+        path.keyframes.iter().map(|keyframe| keyframe.time).collect()
+    }
+
+    #[test]
+    fn recording_starts_stops_and_appends() {
+        let mut flythrough = Flythrough::default();
+        let mut path = FlythroughPath::default();
+        let start = Instant::now();
+
+        record_at(&mut flythrough, &mut path, start);
+        assert!(flythrough.recording.is_some());
+        record_at(&mut flythrough, &mut path, start + Duration::from_secs(3));
+        assert_eq!(times(&path), [0.0, 3.0]);
+
+        assert!(flythrough.stop_recording());
+        assert!(flythrough.recording.is_none());
+        assert!(!flythrough.stop_recording());
+
+        // A later recording continues the path instead of starting over.
+        let resumed = start + Duration::from_secs(60);
+        record_at(&mut flythrough, &mut path, resumed);
+        record_at(&mut flythrough, &mut path, resumed + Duration::from_secs(1));
+        assert_eq!(times(&path), [0.0, 3.0, 3.0 + APPEND_GAP, 4.0 + APPEND_GAP]);
+    }
+
+    #[test]
+    fn recording_extends_a_loaded_path() {
+        let keyframe = |time| Keyframe {
+            position: [0.0; 3],
+            rotation: Quat::IDENTITY.to_array(),
+            time,
+        };
+        let mut path = FlythroughPath {
+            keyframes: vec![keyframe(0.0), keyframe(5.0)],
+        };
+        let mut flythrough = Flythrough::default();
+
+        record_at(&mut flythrough, &mut path, Instant::now());
+        assert_eq!(times(&path), [0.0, 5.0, 5.0 + APPEND_GAP]);
+    }
+
+    #[test]
+    fn clearing_ends_the_recording() {
+        let mut flythrough = Flythrough::default();
+        let mut path = FlythroughPath::default();
+        let start = Instant::now();
+
+        record_at(&mut flythrough, &mut path, start);
+        record_at(&mut flythrough, &mut path, start + Duration::from_secs(2));
+        flythrough.clear(&mut path);
+        assert!(path.keyframes.is_empty());
+        assert!(flythrough.recording.is_none());
+
+        record_at(&mut flythrough, &mut path, start + Duration::from_secs(10));
+        assert_eq!(times(&path), [0.0]);
+    }
+}
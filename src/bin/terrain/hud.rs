@@ -0,0 +1,154 @@
+//! Heads-up display with the frame rate, camera pose and speed, the ground under the camera and
+//! the culling and simplification statistics.
+
+use crate::loading::Stage;
+use crate::picking::PickText;
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use terrain::camera::{CameraController, CameraMode, MainCamera};
+use terrain::culling::TerrainCullingStats;
+use terrain::input::ActionInput;
+use terrain::origin::WorldOrigin;
+use terrain::query::TerrainQuery;
+
+/// Shows the HUD once the terrain is running.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(Stage::Running), setup_hud)
+            .add_systems(Update, update_hud_system.run_if(in_state(Stage::Running)));
+    }
+}
+
+#[derive(Component)]
+struct CoordinateText;
+
+fn setup_hud(mut commands: Commands) {
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.0),
+            top: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(10.0)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 1.0, 1.0)),
+                CoordinateText,
+            ));
+
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.8, 0.6)),
+                Node {
+                    margin: UiRect::left(Val::Px(40.0)),
+                    ..default()
+                },
+                PickText,
+            ));
+        });
+}
+
+fn update_hud_system(
+    mut text_query: Query<&mut Text, With<CoordinateText>>,
+    camera_query: Query<(&Transform, &CameraController), With<MainCamera>>,
+    diagnostics: Res<DiagnosticsStore>,
+    culling_stats: Res<TerrainCullingStats>,
+    world_origin: Res<WorldOrigin>,
+    terrain_query: TerrainQuery,
+    input: ActionInput,
+) {
+    let Ok(mut text) = text_query.single_mut() else {
+        return;
+    };
+
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(0.0);
+
+    // Get camera transform
+    if let Ok((camera_transform, controller)) = camera_query.single() {
+        let pos = world_origin.to_world(camera_transform.translation);
+        let (x, z) = (camera_transform.translation.x, camera_transform.translation.z);
+        let forward = camera_transform.forward();
+
+        let pitch = forward.y.asin().to_degrees();
+
+        let heading = forward.x.atan2(-forward.z).to_degrees();
+        let heading = if heading < 0.0 {
+            heading + 360.0
+        } else {
+            heading
+        };
+
+        let simplification_savings = if culling_stats.visible_full_triangles > 0 {
+            100.0
+                * (1.0
+                    - culling_stats.visible_triangles as f32
+                        / culling_stats.visible_full_triangles as f32)
+        } else {
+            0.0
+        };
+
+        let speed_multiplier = controller.speed_multiplier(&input);
+        let motion = match controller.mode {
+            CameraMode::Fly => {
+                let altitude = terrain_query
+                    .height_at(x, z)
+                    .map(|ground| camera_transform.translation.y - ground);
+                let speed = controller.fly_speed(altitude) * speed_multiplier;
+                format!("Speed: {:.0} m/s", speed)
+            }
+            CameraMode::Walk => {
+                format!("Speed: {:.0} m/s", controller.walk_speed * speed_multiplier)
+            }
+            CameraMode::Orbit => format!("Orbit distance: {:.0} m", controller.orbit_distance),
+        };
+
+        let ground = match (
+            terrain_query.height_at(x, z),
+            terrain_query.slope_at(x, z),
+            terrain_query.biome_at(x, z),
+        ) {
+            (Some(height), Some(slope), Some(biome)) => format!(
+                "{:.1} m, {:.1} deg slope, {:?}",
+                height,
+                slope.to_degrees(),
+                biome
+            ),
+            _ => "none".to_string(),
+        };
+
+        text.0 = format!(
+            "FPS: {:.1}\n\nCoord: ({:.1},{:.1},{:.1})\nPitch: {:.1} deg\nHeading: {:.1} deg\nMode: {:?}\n{}\nGround: {}\n\nChunks: {}/{}\nTriangles: {}/{}\nSimplification saves: {:.1}%",
+            fps,
+            pos.x,
+            pos.y,
+            pos.z,
+            pitch,
+            heading,
+            controller.mode,
+            motion,
+            ground,
+            culling_stats.visible_chunks,
+            culling_stats.total_chunks,
+            culling_stats.visible_triangles,
+            culling_stats.total_triangles,
+            simplification_savings
+        );
+    } else {
+        text.0 = format!("FPS: {:.1}\n", fps);
+    }
+}
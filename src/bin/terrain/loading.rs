@@ -0,0 +1,157 @@
+//! Loading screen shown while the terrain is generated, with real progress and an estimate of the
+//! time remaining.
+
+use bevy::prelude::*;
+use terrain::progress::TerrainGenerationProgress;
+use terrain::terrain::TerrainManager;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum Stage {
+    #[default]
+    Loading,
+    Running,
+}
+
+/// Shows the loading screen in [`Stage::Loading`] and enters [`Stage::Running`] once the terrain
+/// is loaded.
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<Stage>()
+            .add_systems(Startup, setup_loading_screen)
+            .add_systems(
+                Update,
+                (update_loading_screen, start_game).run_if(in_state(Stage::Loading)),
+            )
+            .add_systems(OnEnter(Stage::Running), cleanup_loading_screen);
+    }
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingText;
+
+#[derive(Component)]
+struct LoadingProgressBar;
+
+#[derive(Component)]
+struct LoadingStatusText;
+
+fn setup_loading_screen(mut commands: Commands) {
+    commands.spawn((Camera2d, LoadingScreen));
+
+    // Full screen overlay
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.1, 0.1, 0.15)),
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Loading Terrain..."),
+                TextFont {
+                    font_size: 48.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 1.0, 1.0)),
+                LoadingText,
+            ));
+
+            // Progress bar
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(400.0),
+                        height: Val::Px(12.0),
+                        margin: UiRect::top(Val::Px(40.0)),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.2, 0.2, 0.25)),
+                    BorderRadius::all(Val::Px(6.0)),
+                ))
+                .with_children(|bar_parent| {
+                    bar_parent.spawn((
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.3, 0.6, 0.9)),
+                        BorderRadius::all(Val::Px(6.0)),
+                        LoadingProgressBar,
+                    ));
+                });
+
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.7, 0.7, 0.75)),
+                Node {
+                    margin: UiRect::top(Val::Px(16.0)),
+                    ..default()
+                },
+                LoadingStatusText,
+            ));
+        });
+}
+
+fn start_game(mut next_state: ResMut<NextState<Stage>>, terrain_manager: Res<TerrainManager>) {
+    if terrain_manager.loaded {
+        next_state.set(Stage::Running);
+    }
+}
+
+fn update_loading_screen(
+    progress: Option<Res<TerrainGenerationProgress>>,
+    mut bar_query: Query<&mut Node, With<LoadingProgressBar>>,
+    mut text_query: Query<&mut Text, With<LoadingStatusText>>,
+) {
+    let Some(progress) = progress else {
+        return;
+    };
+
+    if let Ok(mut bar) = bar_query.single_mut() {
+        bar.width = Val::Percent(progress.0.fraction() * 100.0);
+    }
+
+    if let Ok(mut text) = text_query.single_mut() {
+        let stage = progress.0.stage();
+        let (completed, total) = progress.0.stage_progress();
+        let eta = match progress.0.eta() {
+            Some(eta) => format!("{:.0}s remaining", eta.as_secs_f32().ceil()),
+            None => "estimating time remaining".to_string(),
+        };
+
+        text.0 = format!(
+            "{}: {}/{} {} - {}",
+            stage.label(),
+            completed,
+            total,
+            stage.unit(),
+            eta
+        );
+    }
+}
+
+fn cleanup_loading_screen(
+    mut commands: Commands,
+    loading_screen_query: Query<Entity, With<LoadingScreen>>,
+) {
+    for entity in loading_screen_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
+use bevy::pbr::wireframe::{WireframeConfig, WireframePlugin};
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, CursorOptions, PresentMode};
+use terrain::cache::DEFAULT_CACHE_DIR;
+use terrain::camera::{self, CameraControllerPlugin, CameraControllerSystems};
+use terrain::input;
+use terrain::origin;
+use terrain::terrain::{
+    toggle_normals_system, toggle_simplification_system, toggle_wireframe_system, TerrainPlugin,
+    TerrainSettings,
+};
+
+mod benchmark;
+mod bookmarks;
+mod camera_widget;
+mod cli;
+mod flythrough;
+mod hud;
+mod loading;
+mod picking;
+mod scene;
+mod screenshot;
+mod settings;
+
+use crate::benchmark::Benchmark;
+use crate::flythrough::Flythrough;
+use crate::hud::HudPlugin;
+use crate::loading::{LoadingScreenPlugin, Stage};
+use crate::picking::TerrainPick;
+use crate::screenshot::{CommandLineCapture, ScreenshotCapture};
+use crate::settings::SettingsScreen;
+use camera_widget::{setup_camera_widget, CameraWidgetPlugin};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let benchmark = cli::benchmark_args(&args);
+    let command_line_capture = cli::capture_args(&args);
+
+    // Benchmarks run uncapped so that frame times are not hidden behind vsync.
+    let present_mode = if benchmark.is_some() {
+        PresentMode::AutoNoVsync
+    } else {
+        PresentMode::default()
+    };
+    let window_plugin = WindowPlugin {
+        primary_window: Some(Window {
+            present_mode,
+            ..default()
+        }),
+        ..default()
+    };
+
+    // A capture reproducing an earlier screenshot needs the terrain it was taken of.
+    let settings = command_line_capture
+        .as_ref()
+        .and_then(|capture| capture.source.as_ref())
+        .map_or_else(TerrainSettings::default, |source| source.settings.clone());
+
+    // Benchmarks time a full generation every run.
+    let terrain_plugin = if benchmark.is_some() {
+        TerrainPlugin::new(settings)
+    } else {
+        TerrainPlugin::new(settings).with_cache(DEFAULT_CACHE_DIR)
+    };
+
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(window_plugin))
+        .add_plugins(WireframePlugin::default())
+        .add_plugins(FrameTimeDiagnosticsPlugin::default()) // Add FPS diagnostics
+        .add_plugins((terrain_plugin, CameraControllerPlugin))
+        .add_plugins((LoadingScreenPlugin, HudPlugin, CameraWidgetPlugin))
+        .insert_resource(WireframeConfig {
+            global: false,
+            default_color: Color::srgb(1.0, 1.0, 0.0), // Yellow wireframe
+        })
+        .init_resource::<TerrainPick>()
+        .init_resource::<SettingsScreen>()
+        .init_resource::<Flythrough>()
+        .init_resource::<ScreenshotCapture>()
+        .insert_resource(input::load_input_bindings())
+        .insert_resource(flythrough::load_flythrough_path())
+        .configure_sets(
+            Update,
+            CameraControllerSystems
+                .run_if(in_state(Stage::Running))
+                .run_if(settings::settings_closed)
+                .run_if(benchmark::not_benchmarking),
+        )
+        .add_systems(
+            Startup,
+            (
+                bookmarks::load_bookmarks,
+                screenshot::pause_for_command_line_capture
+                    .run_if(resource_exists::<CommandLineCapture>),
+                benchmark::start_generation_timer.run_if(resource_exists::<Benchmark>),
+            ),
+        )
+        .add_systems(
+            OnEnter(Stage::Running),
+            (
+                scene::setup_environment,
+                setup_cursor.run_if(benchmark::not_benchmarking),
+                setup_camera_widget,
+                picking::setup_pick_marker,
+                benchmark::stop_generation_timer.run_if(resource_exists::<Benchmark>),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    toggle_wireframe_system,
+                    toggle_normals_system,
+                    toggle_simplification_system,
+                    bookmarks::bookmark_keys_system,
+                    flythrough::flythrough_keys_system,
+                    screenshot::screenshot_keys_system,
+                )
+                    .run_if(settings::settings_closed)
+                    .run_if(benchmark::not_benchmarking),
+                bookmarks::bookmark_flight_system
+                    .after(camera::camera_movement)
+                    .after(bookmarks::bookmark_keys_system)
+                    .before(origin::recenter_origin_system),
+                flythrough::flythrough_playback_system
+                    .after(camera::camera_movement)
+                    .after(flythrough::flythrough_keys_system)
+                    .before(origin::recenter_origin_system),
+                benchmark::benchmark_frame_system
+                    .after(flythrough::flythrough_playback_system)
+                    .run_if(resource_exists::<Benchmark>),
+                (
+                    screenshot::command_line_capture_system
+                        .run_if(resource_exists::<CommandLineCapture>),
+                    screenshot::capture_tiles_system,
+                )
+                    .chain()
+                    .after(origin::recenter_origin_system),
+                (
+                    settings::toggle_settings_system,
+                    settings::capture_rebinding_system.after(settings::toggle_settings_system),
+                    settings::binding_button_system.after(settings::capture_rebinding_system),
+                    settings::update_binding_texts_system.after(settings::binding_button_system),
+                    settings::scroll_bindings_system,
+                ),
+                picking::terrain_picking_system.after(origin::recenter_origin_system),
+                picking::update_pick_text_system.after(picking::terrain_picking_system),
+                scene::dynamic_scene,
+            )
+                .run_if(in_state(Stage::Running)),
+        );
+
+    if let Some((benchmark, path)) = benchmark {
+        app.insert_resource(benchmark).insert_resource(path);
+    }
+    if let Some(capture) = command_line_capture {
+        app.insert_resource(capture);
+    }
+
+    app.run();
+}
+
+fn setup_cursor(mut cursor_options: Single<&mut CursorOptions>) {
+    cursor_options.visible = false;
+    cursor_options.grab_mode = CursorGrabMode::Locked;
+}
+
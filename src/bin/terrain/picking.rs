@@ -0,0 +1,118 @@
+use bevy::light::NotShadowCaster;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, CursorOptions, PrimaryWindow};
+use terrain::camera::MainCamera;
+use terrain::origin::WorldOrigin;
+use terrain::query::{Biome, TerrainQuery};
+
+/// Distance up to which the terrain under the cursor is picked.
+const MAX_PICK_DISTANCE: f32 = 10000.0;
+
+/// Radius of the pick marker as a fraction of its distance to the camera, so that it keeps the
+/// same size on screen.
+const MARKER_SCALE: f32 = 0.005;
+
+/// Terrain point under the cursor while the cursor is released.
+#[derive(Resource, Default)]
+pub struct TerrainPick(pub Option<PickedPoint>);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PickedPoint {
+    /// World-space position of the hit.
+    pub position: DVec3,
+    /// Angle, in radians, between the ground and the horizontal.
+    pub slope: f32,
+    pub biome: Biome,
+}
+
+#[derive(Component)]
+pub struct PickText;
+
+/// Small disc lying on the terrain under the cursor.
+#[derive(Component)]
+pub struct PickMarker;
+
+pub fn setup_pick_marker(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        PickMarker,
+        Mesh3d(meshes.add(Cylinder::new(1.0, 0.2))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::srgb(1.0, 0.2, 0.1),
+            unlit: true,
+            ..default()
+        })),
+        NotShadowCaster,
+        Transform::default(),
+        Visibility::Hidden,
+    ));
+}
+
+/// Casts a ray through the cursor and moves the marker to where it meets the terrain.
+pub fn terrain_picking_system(
+    window: Single<&Window, With<PrimaryWindow>>,
+    cursor_options: Single<&CursorOptions>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    terrain_query: TerrainQuery,
+    world_origin: Res<WorldOrigin>,
+    mut pick: ResMut<TerrainPick>,
+    mut marker_query: Query<(&mut Transform, &mut Visibility), With<PickMarker>>,
+) {
+    pick.0 = None;
+
+    // The cursor only points at anything while it is released.
+    if cursor_options.grab_mode == CursorGrabMode::None
+        && let Some(cursor) = window.cursor_position()
+        && let Ok((camera, camera_transform)) = camera_query.single()
+        && let Ok(ray) = camera.viewport_to_world(camera_transform, cursor)
+        && let Some(hit) = terrain_query.raycast(ray, MAX_PICK_DISTANCE)
+        && let Some(slope) = terrain_query.slope_at(hit.position.x, hit.position.z)
+        && let Some(biome) = terrain_query.biome_at(hit.position.x, hit.position.z)
+    {
+        pick.0 = Some(PickedPoint {
+            position: world_origin.to_world(hit.position),
+            slope,
+            biome,
+        });
+
+        if let Ok((mut transform, _)) = marker_query.single_mut() {
+            transform.translation = hit.position;
+            transform.rotation = Quat::from_rotation_arc(Vec3::Y, hit.normal);
+            transform.scale = Vec3::splat(hit.distance * MARKER_SCALE);
+        }
+    }
+
+    if let Ok((_, mut visibility)) = marker_query.single_mut() {
+        visibility.set_if_neq(if pick.0.is_some() {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+pub fn update_pick_text_system(
+    pick: Res<TerrainPick>,
+    mut text_query: Query<&mut Text, With<PickText>>,
+) {
+    let Ok(mut text) = text_query.single_mut() else {
+        return;
+    };
+
+    text.0 = match pick.0 {
+        Some(point) => format!(
+            "Cursor: ({:.1},{:.1},{:.1})\nHeight: {:.1} m\nSlope: {:.1} deg\nBiome: {:?}",
+            point.position.x,
+            point.position.y,
+            point.position.z,
+            point.position.y,
+            point.slope.to_degrees(),
+            point.biome
+        ),
+        None => String::new(),
+    };
+}
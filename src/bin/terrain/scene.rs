@@ -0,0 +1,59 @@
+//! The demo's camera, sunlight and day cycle.
+
+use bevy::camera::Exposure;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::light::light_consts::lux;
+use bevy::light::{AtmosphereEnvironmentMapLight, CascadeShadowConfigBuilder};
+use bevy::pbr::Atmosphere;
+use bevy::post_process::bloom::Bloom;
+use bevy::prelude::*;
+use std::f32::consts::PI;
+use terrain::camera::{CameraController, MainCamera};
+
+pub fn setup_environment(mut commands: Commands) {
+    let position = Vec3::new(71.0, 406.0, 1008.0);
+    let pitch = -10.0_f32.to_radians();
+    let heading = 335.0_f32.to_radians();
+    commands.spawn((
+        camera_effects(),
+        CameraController::default(),
+        Transform::from_translation(position).with_rotation(Quat::from_rotation_y(-heading) * Quat::from_rotation_x(pitch)),
+        MainCamera,
+    ));
+
+    let cascade_shadow_config = CascadeShadowConfigBuilder {
+        num_cascades: 4,
+        first_cascade_far_bound: 20.0,
+        maximum_distance: 2000.0,
+        overlap_proportion: 0.15,
+        ..default()
+    }
+        .build();
+
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            illuminance: lux::RAW_SUNLIGHT,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 1.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+        cascade_shadow_config
+    ));
+}
+
+/// Rendering components of the main view, shared with the offscreen screenshot camera.
+pub fn camera_effects() -> impl Bundle {
+    (
+        Camera3d::default(),
+        Atmosphere::EARTH,
+        Exposure::SUNLIGHT,
+        Bloom::NATURAL,
+        AtmosphereEnvironmentMapLight::default(),
+        Tonemapping::AcesFitted,
+    )
+}
+
+pub fn dynamic_scene(mut suns: Query<&mut Transform, With<DirectionalLight>>, time: Res<Time>) {
+    suns.iter_mut()
+        .for_each(|mut tf| tf.rotate_y(-time.delta_secs() * PI / 10.0));
+}
@@ -0,0 +1,439 @@
+use crate::bookmarks::Bookmark;
+use bevy::camera::{RenderTarget, SubCameraView};
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::window::PrimaryWindow;
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use terrain::camera::MainCamera;
+use terrain::input::{ActionInput, InputAction};
+use terrain::origin::WorldOrigin;
+use terrain::terrain::TerrainSettings;
+
+/// Directory, relative to the working directory, screenshots taken with the hotkeys are saved to.
+pub const DEFAULT_SCREENSHOT_DIR: &str = "screenshots";
+
+/// Output size of the poster capture.
+pub const POSTER_SIZE: UVec2 = UVec2::new(7680, 4320);
+
+/// Samples per pixel along each axis of the poster capture.
+pub const POSTER_SUPERSAMPLE: u32 = 2;
+
+/// Largest tile rendered in one pass. Larger captures are rendered tile by tile.
+const MAX_TILE_SIZE: u32 = 4096;
+
+/// Frames rendered into a tile before it is captured, so that shadows and the atmosphere have
+/// settled after the offscreen camera was moved to it.
+const TILE_SETTLE_FRAMES: u32 = 5;
+
+/// A capture of the main view to a PNG file, with a sidecar describing how to reproduce it.
+#[derive(Debug, Clone)]
+pub struct CaptureRequest {
+    pub path: PathBuf,
+    /// Size of the saved image.
+    pub size: UVec2,
+    /// Samples per pixel along each axis, averaged down to the saved image.
+    pub supersample: u32,
+}
+
+/// Reproduction details saved next to every screenshot, with the same name and a `.ron`
+/// extension.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenshotInfo {
+    pub settings: TerrainSettings,
+    pub camera: Bookmark,
+    pub vertical_fov_degrees: f32,
+    /// Rotation of the sun, which sets the time of day. Sidecars of older captures lack it.
+    #[serde(default)]
+    pub sun_rotation: Option<[f32; 4]>,
+    pub size: [u32; 2],
+    pub supersample: u32,
+}
+
+impl ScreenshotInfo {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        ron::from_str(&text).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// The capture being rendered, if any.
+#[derive(Resource, Default)]
+pub struct ScreenshotCapture(Option<ActiveCapture>);
+
+impl ScreenshotCapture {
+    pub fn is_busy(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+struct ActiveCapture {
+    request: CaptureRequest,
+    info: ScreenshotInfo,
+    /// World-space pose of the view, fixed when the capture started.
+    position: DVec3,
+    rotation: Quat,
+    camera: Entity,
+    target: Handle<Image>,
+    tile_size: UVec2,
+    tiles: Vec<UVec2>,
+    next_tile: usize,
+    frames_waited: u32,
+    in_flight: bool,
+    /// Full supersampled image, assembled from the tiles.
+    pixels: RgbaImage,
+}
+
+/// Offscreen camera rendering the tiles of a capture.
+#[derive(Component)]
+pub struct ScreenshotCamera;
+
+/// Builds a timestamped path in [`DEFAULT_SCREENSHOT_DIR`].
+fn timestamped_path(prefix: &str) -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    PathBuf::from(DEFAULT_SCREENSHOT_DIR).join(format!("{prefix}-{seconds}.png"))
+}
+
+/// Takes a screenshot at the window size, or a tiled, supersampled poster.
+pub fn screenshot_keys_system(
+    mut commands: Commands,
+    input: ActionInput,
+    capture: Res<ScreenshotCapture>,
+    window: Single<&Window, With<PrimaryWindow>>,
+) {
+    if capture.is_busy() {
+        return;
+    }
+
+    let request = if input.just_pressed(InputAction::Screenshot) {
+        CaptureRequest {
+            path: timestamped_path("screenshot"),
+            size: window.physical_size(),
+            supersample: 1,
+        }
+    } else if input.just_pressed(InputAction::CapturePoster) {
+        CaptureRequest {
+            path: timestamped_path("poster"),
+            size: POSTER_SIZE,
+            supersample: POSTER_SUPERSAMPLE,
+        }
+    } else {
+        return;
+    };
+
+    commands.run_system_cached_with(start_capture, request);
+}
+
+/// Spawns the offscreen camera of a capture at the current [`MainCamera`] pose and pauses virtual
+/// time, so that the sun does not move between tiles.
+#[allow(clippy::too_many_arguments)]
+pub fn start_capture(
+    In(request): In<CaptureRequest>,
+    mut commands: Commands,
+    mut capture: ResMut<ScreenshotCapture>,
+    mut images: ResMut<Assets<Image>>,
+    mut time: ResMut<Time<Virtual>>,
+    settings: Res<TerrainSettings>,
+    world_origin: Res<WorldOrigin>,
+    camera_query: Query<(&Transform, &Projection), With<MainCamera>>,
+    sun_query: Query<&Transform, With<DirectionalLight>>,
+) {
+    let Ok((transform, projection)) = camera_query.single() else {
+        return;
+    };
+    if capture.is_busy() {
+        warn!("A capture is already in progress");
+        return;
+    }
+
+    // A minimized window has no pixels to capture, and zero tiles would divide by zero below.
+    if request.size.min_element() == 0 {
+        warn!(
+            "Cannot capture a {}x{} image, skipping {}",
+            request.size.x,
+            request.size.y,
+            request.path.display()
+        );
+        return;
+    }
+
+    let full_size = request.size * request.supersample.max(1);
+    let tiles_per_side = full_size.map(|size| size.div_ceil(MAX_TILE_SIZE));
+    let tile_size = (full_size.as_vec2() / tiles_per_side.as_vec2()).ceil().as_uvec2();
+    let tiles = (0..tiles_per_side.y)
+        .flat_map(|row| (0..tiles_per_side.x).map(move |col| UVec2::new(col, row) * tile_size))
+        .collect();
+
+    let size = Extent3d {
+        width: tile_size.x,
+        height: tile_size.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+    let target = images.add(image);
+
+    let camera = commands
+        .spawn((
+            crate::scene::camera_effects(),
+            Camera {
+                order: -2,
+                target: RenderTarget::Image(target.clone().into()),
+                ..default()
+            },
+            projection.clone(),
+            *transform,
+            ScreenshotCamera,
+        ))
+        .id();
+
+    let vertical_fov_degrees = match projection {
+        Projection::Perspective(perspective) => perspective.fov.to_degrees(),
+        _ => 0.0,
+    };
+
+    time.pause();
+    info!(
+        "Capturing {}x{} at {}x supersampling in {} tiles",
+        request.size.x,
+        request.size.y,
+        request.supersample,
+        tiles_per_side.element_product()
+    );
+
+    capture.0 = Some(ActiveCapture {
+        info: ScreenshotInfo {
+            settings: settings.clone(),
+            camera: Bookmark::from_transform(transform, &world_origin),
+            vertical_fov_degrees,
+            sun_rotation: sun_query.single().ok().map(|sun| sun.rotation.to_array()),
+            size: request.size.to_array(),
+            supersample: request.supersample,
+        },
+        request,
+        position: world_origin.to_world(transform.translation),
+        rotation: transform.rotation,
+        camera,
+        target,
+        tile_size,
+        tiles,
+        next_tile: 0,
+        frames_waited: 0,
+        in_flight: false,
+        pixels: RgbaImage::new(full_size.x, full_size.y),
+    });
+}
+
+/// Moves the offscreen camera from tile to tile, requesting a screenshot of each, and saves the
+/// assembled image once every tile has come back.
+pub fn capture_tiles_system(
+    mut commands: Commands,
+    mut capture: ResMut<ScreenshotCapture>,
+    mut images: ResMut<Assets<Image>>,
+    mut time: ResMut<Time<Virtual>>,
+    world_origin: Res<WorldOrigin>,
+    mut camera_query: Query<(&mut Camera, &mut Transform), With<ScreenshotCamera>>,
+) {
+    let Some(active) = capture.0.as_mut() else {
+        return;
+    };
+    if active.in_flight {
+        return;
+    }
+
+    let Some(&offset) = active.tiles.get(active.next_tile) else {
+        let active = capture.0.take().unwrap();
+        commands.entity(active.camera).despawn();
+        images.remove(&active.target);
+        time.unpause();
+
+        match save_capture(active) {
+            Ok(path) => info!("Saved capture to {}", path.display()),
+            Err(error) => error!("Failed to save capture: {error}"),
+        }
+        return;
+    };
+
+    // The origin may be recentred while capturing, so the fixed world pose is reapplied each frame.
+    if let Ok((mut camera, mut transform)) = camera_query.get_mut(active.camera) {
+        transform.translation = world_origin.to_render(active.position);
+        transform.rotation = active.rotation;
+
+        let sub_view = SubCameraView {
+            full_size: active.request.size * active.request.supersample.max(1),
+            offset: offset.as_vec2(),
+            size: active.tile_size,
+        };
+        if camera.sub_camera_view != Some(sub_view) {
+            camera.sub_camera_view = Some(sub_view);
+            active.frames_waited = 0;
+        }
+    }
+
+    active.frames_waited += 1;
+    if active.frames_waited < TILE_SETTLE_FRAMES {
+        return;
+    }
+
+    active.in_flight = true;
+    commands.spawn(Screenshot::image(active.target.clone())).observe(
+        move |captured: On<ScreenshotCaptured>, mut capture: ResMut<ScreenshotCapture>| {
+            let Some(active) = capture.0.as_mut() else {
+                return;
+            };
+
+            match captured.image.clone().try_into_dynamic() {
+                Ok(tile) => imageops::replace(
+                    &mut active.pixels,
+                    &tile.to_rgba8(),
+                    offset.x as i64,
+                    offset.y as i64,
+                ),
+                Err(error) => error!("Failed to read capture tile: {error}"),
+            }
+
+            active.next_tile += 1;
+            active.in_flight = false;
+        },
+    );
+}
+
+/// Averages the supersampled image down to its output size and writes it with its sidecar.
+fn save_capture(active: ActiveCapture) -> io::Result<PathBuf> {
+    let ActiveCapture {
+        request,
+        info,
+        pixels,
+        ..
+    } = active;
+
+    let pixels = downsample(&pixels, request.supersample.max(1));
+    // The alpha channel holds brightness when rendering in HDR, so it is dropped.
+    let rgb = DynamicImage::ImageRgba8(pixels).to_rgb8();
+
+    if let Some(dir) = request.path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    rgb.save(&request.path).map_err(io::Error::other)?;
+
+    let text = ron::ser::to_string_pretty(&info, ron::ser::PrettyConfig::default())
+        .map_err(io::Error::other)?;
+    fs::write(request.path.with_extension("ron"), text)?;
+
+    Ok(request.path)
+}
+
+/// Box-filters blocks of `factor`² pixels into one.
+fn downsample(pixels: &RgbaImage, factor: u32) -> RgbaImage {
+    if factor == 1 {
+        return pixels.clone();
+    }
+
+    let samples = factor * factor;
+    RgbaImage::from_fn(pixels.width() / factor, pixels.height() / factor, |x, y| {
+        let mut sum = [0u32; 4];
+        for dy in 0..factor {
+            for dx in 0..factor {
+                let pixel = pixels.get_pixel(x * factor + dx, y * factor + dy);
+                for (total, channel) in sum.iter_mut().zip(pixel.0) {
+                    *total += channel as u32;
+                }
+            }
+        }
+        Rgba(sum.map(|total| ((total + samples / 2) / samples) as u8))
+    })
+}
+
+/// A capture requested on the command line, taken once the terrain has loaded and the view has
+/// warmed up. The app exits when it has been saved.
+#[derive(Resource)]
+pub struct CommandLineCapture {
+    pub request: CaptureRequest,
+    /// Sidecar of an earlier capture to reproduce instead of the start view. Its camera and
+    /// lighting are applied here; its terrain settings must be used to build the app.
+    pub source: Option<ScreenshotInfo>,
+    frames_waited: u32,
+    started: bool,
+}
+
+impl CommandLineCapture {
+    pub fn new(request: CaptureRequest, source: Option<ScreenshotInfo>) -> Self {
+        Self {
+            request,
+            source,
+            frames_waited: 0,
+            started: false,
+        }
+    }
+}
+
+/// Pauses virtual time for the whole run, so that the sun stays where it starts and captures of
+/// the same pose come out the same.
+pub fn pause_for_command_line_capture(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+/// Frames rendered before a command-line capture starts.
+const COMMAND_LINE_WARMUP_FRAMES: u32 = 60;
+
+pub fn command_line_capture_system(
+    mut commands: Commands,
+    mut cli: ResMut<CommandLineCapture>,
+    capture: Res<ScreenshotCapture>,
+    world_origin: Res<WorldOrigin>,
+    mut camera_query: Query<(&mut Transform, &mut Projection), With<MainCamera>>,
+    mut sun_query: Query<&mut Transform, (With<DirectionalLight>, Without<MainCamera>)>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if cli.started {
+        if !capture.is_busy() {
+            exit.write(AppExit::Success);
+        }
+        return;
+    }
+
+    if let Some(source) = &cli.source {
+        if let Ok((mut transform, mut projection)) = camera_query.single_mut() {
+            let bookmark = source.camera;
+            transform.translation = world_origin.to_render(DVec3::from_array(bookmark.position));
+            transform.rotation = bookmark.rotation();
+            if let Projection::Perspective(perspective) = projection.as_mut() {
+                perspective.fov = source.vertical_fov_degrees.to_radians();
+            }
+        }
+        if let (Some(rotation), Ok(mut sun)) = (source.sun_rotation, sun_query.single_mut()) {
+            sun.rotation = Quat::from_array(rotation);
+        }
+    }
+
+    cli.frames_waited += 1;
+    if cli.frames_waited >= COMMAND_LINE_WARMUP_FRAMES {
+        cli.started = true;
+        commands.run_system_cached_with(start_capture, cli.request.clone());
+    }
+}
@@ -0,0 +1,273 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::{CursorGrabMode, CursorOptions};
+use terrain::input::{
+    ActionInput, Binding, InputAction, InputBindings, CANCEL_REBINDING, DEFAULT_BINDINGS_PATH,
+};
+
+/// State of the input settings screen.
+#[derive(Resource, Default)]
+pub struct SettingsScreen {
+    /// Bindings as they were when the screen was opened, or `None` while it is closed.
+    opened_with: Option<InputBindings>,
+    /// Action waiting for its new input.
+    rebinding: Option<InputAction>,
+}
+
+/// Run condition for the systems that react to gameplay input.
+pub fn settings_closed(screen: Res<SettingsScreen>) -> bool {
+    screen.opened_with.is_none()
+}
+
+#[derive(Component)]
+pub struct SettingsRoot;
+
+/// Scrolling list of the [`BindingButton`]s.
+#[derive(Component)]
+pub struct BindingList;
+
+#[derive(Component)]
+pub struct BindingButton(InputAction);
+
+#[derive(Component)]
+pub struct BindingText(InputAction);
+
+/// Opens and closes the settings screen, saving the bindings if they were changed.
+pub fn toggle_settings_system(
+    mut commands: Commands,
+    input: ActionInput,
+    bindings: Res<InputBindings>,
+    mut screen: ResMut<SettingsScreen>,
+    mut cursor_options: Single<&mut CursorOptions>,
+    root_query: Query<Entity, With<SettingsRoot>>,
+) {
+    // While rebinding, the key belongs to the action being rebound.
+    if screen.rebinding.is_some() || !input.just_pressed(InputAction::OpenSettings) {
+        return;
+    }
+
+    match screen.opened_with.take() {
+        Some(opened_with) => {
+            for entity in root_query.iter() {
+                commands.entity(entity).despawn();
+            }
+
+            if *bindings != opened_with {
+                match bindings.save(DEFAULT_BINDINGS_PATH) {
+                    Ok(()) => info!("Saved input bindings to {DEFAULT_BINDINGS_PATH}"),
+                    Err(error) => warn!("Failed to save {DEFAULT_BINDINGS_PATH}: {error}"),
+                }
+            }
+        }
+        None => {
+            screen.opened_with = Some(bindings.clone());
+            cursor_options.visible = true;
+            cursor_options.grab_mode = CursorGrabMode::None;
+            spawn_settings_screen(&mut commands, &bindings);
+        }
+    }
+}
+
+fn spawn_settings_screen(commands: &mut Commands, bindings: &InputBindings) {
+    let close_inputs: Vec<String> = bindings
+        .get(InputAction::OpenSettings)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.1, 0.1, 0.15, 0.9)),
+            GlobalZIndex(1),
+            SettingsRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Input bindings"),
+                TextFont {
+                    font_size: 32.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 1.0, 1.0)),
+                Node {
+                    margin: UiRect::bottom(Val::Px(16.0)),
+                    ..default()
+                },
+            ));
+
+            // Not every row fits on smaller windows, so the list scrolls with the mouse wheel.
+            parent
+                .spawn((
+                    Node {
+                        max_height: Val::Percent(70.0),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(4.0),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    ScrollPosition::default(),
+                    BindingList,
+                ))
+                .with_children(|parent| {
+                    for action in InputAction::ALL {
+                        parent
+                            .spawn((
+                                Button,
+                                Node {
+                                    width: Val::Px(600.0),
+                                    flex_shrink: 0.0,
+                                    padding: UiRect::axes(Val::Px(12.0), Val::Px(4.0)),
+                                    ..default()
+                                },
+                                BackgroundColor(Color::srgb(0.2, 0.2, 0.25)),
+                                BindingButton(action),
+                            ))
+                            .with_children(|button| {
+                                button.spawn((
+                                    Text::default(),
+                                    TextFont {
+                                        font_size: 18.0,
+                                        ..default()
+                                    },
+                                    TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                                    BindingText(action),
+                                ));
+                            });
+                    }
+                });
+
+            parent.spawn((
+                Text::new(format!(
+                    "Click an action, then press its new key or button, which replaces its \
+                     inputs of the same kind. Scroll for more actions. Press {} to save and close.",
+                    close_inputs.join(" or ")
+                )),
+                TextFont {
+                    font_size: 16.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.7, 0.7, 0.75)),
+                Node {
+                    margin: UiRect::top(Val::Px(16.0)),
+                    ..default()
+                },
+            ));
+        });
+}
+
+/// Distance, in logical pixels, the binding list scrolls per mouse wheel notch.
+const SCROLL_LINE_HEIGHT: f32 = 32.0;
+
+/// Scrolls the binding list with the mouse wheel.
+pub fn scroll_bindings_system(
+    mut mouse_wheel_events: MessageReader<MouseWheel>,
+    mut list_query: Query<(&mut ScrollPosition, &ComputedNode), With<BindingList>>,
+) {
+    let scroll: f32 = mouse_wheel_events
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y * SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        })
+        .sum();
+    if scroll == 0.0 {
+        return;
+    }
+
+    for (mut position, node) in list_query.iter_mut() {
+        let max_offset = (node.content_size().y - node.size().y) * node.inverse_scale_factor();
+        position.y = (position.y - scroll).clamp(0.0, max_offset.max(0.0));
+    }
+}
+
+/// Starts rebinding the action whose row was clicked.
+pub fn binding_button_system(
+    mut screen: ResMut<SettingsScreen>,
+    button_query: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            screen.rebinding = Some(button.0);
+        }
+    }
+}
+
+/// Binds the first input pressed while an action is waiting to be rebound, or cancels rebinding
+/// when that input is [`CANCEL_REBINDING`].
+///
+/// Runs before [`binding_button_system`] so that the click selecting an action is not taken as
+/// its new binding.
+pub fn capture_rebinding_system(
+    mut screen: ResMut<SettingsScreen>,
+    mut bindings: ResMut<InputBindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+) {
+    let Some(action) = screen.rebinding else {
+        return;
+    };
+
+    let pressed = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| {
+            mouse_buttons
+                .get_just_pressed()
+                .next()
+                .map(|button| Binding::Mouse(*button))
+        })
+        .or_else(|| {
+            gamepads
+                .iter()
+                .find_map(|gamepad| gamepad.get_just_pressed().next())
+                .map(|button| Binding::Gamepad(*button))
+        });
+
+    if let Some(binding) = pressed {
+        if binding == CANCEL_REBINDING {
+            info!("Cancelled rebinding {}", action.label());
+        } else {
+            bindings.rebind(action, binding);
+        }
+        screen.rebinding = None;
+    }
+}
+
+pub fn update_binding_texts_system(
+    screen: Res<SettingsScreen>,
+    bindings: Res<InputBindings>,
+    mut text_query: Query<(&mut Text, &BindingText)>,
+    added_query: Query<(), Added<BindingText>>,
+) {
+    if !screen.is_changed() && !bindings.is_changed() && added_query.is_empty() {
+        return;
+    }
+
+    for (mut text, binding_text) in text_query.iter_mut() {
+        let action = binding_text.0;
+        let inputs = if screen.rebinding == Some(action) {
+            format!("press a key or button, or {CANCEL_REBINDING} to cancel...")
+        } else {
+            let inputs: Vec<String> = bindings.get(action).iter().map(ToString::to_string).collect();
+            if inputs.is_empty() {
+                "unbound".to_string()
+            } else {
+                inputs.join(", ")
+            }
+        };
+
+        text.0 = format!("{}: {}", action.label(), inputs);
+    }
+}
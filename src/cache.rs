@@ -0,0 +1,330 @@
+use crate::terrain::{ChunkData, TerrainSettings, CHUNK_SIZE, GENERATOR_VERSION};
+use bevy::prelude::*;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Directory, relative to the working directory, where the demo caches generated chunks.
+pub const DEFAULT_CACHE_DIR: &str = "terrain_cache";
+
+const MAGIC: &[u8; 4] = b"TRCH";
+
+/// Bump whenever the chunk file layout changes, so that old caches are discarded instead of being
+/// reused. Changes to the generated data itself are covered by [`GENERATOR_VERSION`].
+const FORMAT_VERSION: u32 = 2;
+
+/// Number of settings whose chunks are kept, counting the ones being opened. The least recently
+/// used settings beyond this are removed.
+const MAX_CACHED_SETTINGS: usize = 4;
+
+/// Chunks of settings that have not been used for this long are removed.
+const MAX_UNUSED_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// File whose modification time records when the chunks of a settings directory were last used.
+const LAST_USED_FILE: &str = "last_used";
+
+const HEADER_LEN: usize = 4 + 4 + 8 + 4 + 4 + 4;
+
+/// Bytes stored per vertex: the height, the three normal components and the two splat weights.
+const VERTEX_LEN: usize = 4 * (1 + 3 + 2);
+
+/// On-disk cache of generated chunk data.
+///
+/// Chunks are stored in a sub-directory named after the hash of the [`TerrainSettings`] and the
+/// generator version, so any settings or generator change results in a cache miss. Directories of
+/// other settings are kept for runs that switch back to them, and only removed when opening the
+/// cache finds them unused for [`MAX_UNUSED_AGE`] or beyond the [`MAX_CACHED_SETTINGS`] most
+/// recently used.
+///
+/// Each chunk file holds, in little-endian order, a header followed by the heights, the normals and
+/// the snow and tree splat weights, all as `f32`. Nothing is quantised, so chunks loaded from the
+/// cache are identical to freshly generated ones.
+pub struct ChunkCache {
+    dir: PathBuf,
+    key: u64,
+}
+
+impl ChunkCache {
+    pub fn open(root: impl AsRef<Path>, settings: &TerrainSettings) -> io::Result<Self> {
+        let key = settings_key(settings);
+        let root = root.as_ref();
+        let dir = root.join(format!("{key:016x}"));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(LAST_USED_FILE), [])?;
+
+        if let Err(error) = evict_unused(root, &dir) {
+            warn!("Failed to clean up the terrain cache: {error}");
+        }
+
+        Ok(Self { dir, key })
+    }
+
+    fn chunk_path(&self, coord: IVec2) -> PathBuf {
+        self.dir.join(format!("{}_{}.chunk", coord.x, coord.y))
+    }
+
+    /// Returns the cached data for a chunk, or `None` if it is missing or unreadable.
+    pub fn load(&self, coord: IVec2) -> Option<ChunkData> {
+        let bytes = fs::read(self.chunk_path(coord)).ok()?;
+        self.decode(coord, &bytes)
+    }
+
+    pub fn store(&self, coord: IVec2, data: &ChunkData) -> io::Result<()> {
+        let vertex_count = data.heights.len();
+        let mut bytes = Vec::with_capacity(HEADER_LEN + vertex_count * VERTEX_LEN);
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.key.to_le_bytes());
+        bytes.extend_from_slice(&coord.x.to_le_bytes());
+        bytes.extend_from_slice(&coord.y.to_le_bytes());
+        bytes.extend_from_slice(&(vertex_count as u32).to_le_bytes());
+
+        let values = data
+            .heights
+            .iter()
+            .chain(data.normals.as_flattened())
+            .chain(data.splat.as_flattened());
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        // Write to a temporary file first so that an interrupted run never leaves a truncated
+        // chunk behind.
+        let path = self.chunk_path(coord);
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, &bytes)?;
+        fs::rename(&temp_path, &path)
+    }
+
+    fn decode(&self, coord: IVec2, bytes: &[u8]) -> Option<ChunkData> {
+        let vertex_count = (CHUNK_SIZE + 1) * (CHUNK_SIZE + 1);
+        if bytes.len() != HEADER_LEN + vertex_count * VERTEX_LEN {
+            return None;
+        }
+
+        let (header, body) = bytes.split_at(HEADER_LEN);
+        let valid = &header[0..4] == MAGIC
+            && read_u32(&header[4..]) == FORMAT_VERSION
+            && u64::from_le_bytes(header[8..16].try_into().unwrap()) == self.key
+            && read_u32(&header[16..]) as i32 == coord.x
+            && read_u32(&header[20..]) as i32 == coord.y
+            && read_u32(&header[24..]) as usize == vertex_count;
+        if !valid {
+            return None;
+        }
+
+        let mut values = body
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()));
+
+        let heights = values.by_ref().take(vertex_count).collect();
+        let normals = (0..vertex_count)
+            .map(|_| std::array::from_fn(|_| values.next().unwrap()))
+            .collect();
+        let splat = (0..vertex_count)
+            .map(|_| std::array::from_fn(|_| values.next().unwrap()))
+            .collect();
+
+        Some(ChunkData {
+            heights,
+            normals,
+            splat,
+        })
+    }
+}
+
+/// Removes the directories of other settings that have not been used for [`MAX_UNUSED_AGE`] or
+/// are beyond the [`MAX_CACHED_SETTINGS`] most recently used.
+///
+/// Other runs may be using the cache at the same time, and may be removing the same directories.
+fn evict_unused(root: &Path, current: &Path) -> io::Result<()> {
+    let mut others: Vec<(SystemTime, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path == current || !path.is_dir() || !is_cache_dir_name(&path) {
+            continue;
+        }
+
+        // Directories from before last-use tracking fall back to their own modification time.
+        let last_used = fs::metadata(path.join(LAST_USED_FILE))
+            .or_else(|_| fs::metadata(&path))
+            .and_then(|metadata| metadata.modified());
+        if let Ok(last_used) = last_used {
+            others.push((last_used, path));
+        }
+    }
+
+    // Most recently used first.
+    others.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    let now = SystemTime::now();
+    for (index, (last_used, path)) in others.iter().enumerate() {
+        let unused_for = now.duration_since(*last_used).unwrap_or_default();
+        if index + 1 < MAX_CACHED_SETTINGS && unused_for <= MAX_UNUSED_AGE {
+            continue;
+        }
+
+        info!("Removing unused terrain cache {}", path.display());
+        match fs::remove_dir_all(path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            result => result?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Stable hash of everything that affects the generated chunk data.
+///
+/// This uses FNV-1a rather than `std`'s hasher, whose output is not guaranteed to stay the same
+/// across Rust releases.
+fn settings_key(settings: &TerrainSettings) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    write(&FORMAT_VERSION.to_le_bytes());
+    write(&GENERATOR_VERSION.to_le_bytes());
+    write(&(CHUNK_SIZE as u64).to_le_bytes());
+    write(&settings.seed.to_le_bytes());
+    write(&(settings.resolution as u64).to_le_bytes());
+    write(&settings.amplitude.to_bits().to_le_bytes());
+    write(&settings.scale.to_bits().to_le_bytes());
+
+    hash
+}
+
+fn is_cache_dir_name(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.len() == 16 && name.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[0..4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir()
+            .join(format!("terrain-cache-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        root
+    }
+
+    fn seed(seed: u32) -> TerrainSettings {
+        TerrainSettings {
+            seed,
+            ..default()
+        }
+    }
+
+    fn flat_chunk_data() -> ChunkData {
+        let vertex_count = (CHUNK_SIZE + 1) * (CHUNK_SIZE + 1);
+        ChunkData {
+            heights: vec![1.5; vertex_count],
+            normals: vec![[0.0, 1.0, 0.0]; vertex_count],
+            splat: vec![[0.0, 1.0]; vertex_count],
+        }
+    }
+
+    fn cached_keys(root: &Path) -> Vec<String> {
+        let mut keys: Vec<String> = fs::read_dir(root)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn key_name(settings: &TerrainSettings) -> String {
+        format!("{:016x}", settings_key(settings))
+    }
+
+    #[test]
+    fn cached_chunks_load_unchanged() {
+        let root = temp_root("unchanged");
+        let settings = seed(1);
+        let coord = IVec2::new(2, 3);
+        let data = crate::terrain::generate_chunk_data(&settings, coord);
+
+        let cache = ChunkCache::open(&root, &settings).unwrap();
+        cache.store(coord, &data).unwrap();
+        let loaded = cache.load(coord).unwrap();
+
+        let bits = |values: &[f32]| values.iter().map(|value| value.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&loaded.heights), bits(&data.heights));
+        assert_eq!(bits(loaded.normals.as_flattened()), bits(data.normals.as_flattened()));
+        assert_eq!(bits(loaded.splat.as_flattened()), bits(data.splat.as_flattened()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn alternating_settings_keep_their_chunks() {
+        let root = temp_root("alternating");
+        let data = flat_chunk_data();
+
+        let first = ChunkCache::open(&root, &seed(1)).unwrap();
+        first.store(IVec2::ZERO, &data).unwrap();
+        ChunkCache::open(&root, &seed(2)).unwrap();
+
+        let first = ChunkCache::open(&root, &seed(1)).unwrap();
+        assert!(first.load(IVec2::ZERO).is_some());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn least_recently_used_settings_are_evicted() {
+        let root = temp_root("evicted");
+
+        for seed_value in 1..=MAX_CACHED_SETTINGS as u32 + 2 {
+            ChunkCache::open(&root, &seed(seed_value)).unwrap();
+            // Keep the last-used times apart on file systems with coarse timestamps.
+            let last_used = SystemTime::now() - Duration::from_secs(100 - seed_value as u64);
+            File::options()
+                .write(true)
+                .open(root.join(key_name(&seed(seed_value))).join(LAST_USED_FILE))
+                .unwrap()
+                .set_modified(last_used)
+                .unwrap();
+        }
+
+        let mut expected: Vec<String> = (3..=MAX_CACHED_SETTINGS as u32 + 2)
+            .map(|seed_value| key_name(&seed(seed_value)))
+            .collect();
+        expected.sort();
+        assert_eq!(cached_keys(&root), expected);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn settings_unused_for_a_long_time_are_evicted() {
+        let root = temp_root("expired");
+
+        ChunkCache::open(&root, &seed(1)).unwrap();
+        File::options()
+            .write(true)
+            .open(root.join(key_name(&seed(1))).join(LAST_USED_FILE))
+            .unwrap()
+            .set_modified(SystemTime::now() - MAX_UNUSED_AGE - Duration::from_secs(60))
+            .unwrap();
+        ChunkCache::open(&root, &seed(2)).unwrap();
+
+        assert_eq!(cached_keys(&root), [key_name(&seed(2))]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,94 +1,553 @@
-use bevy::input::mouse::MouseMotion;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use bevy::window::{CursorGrabMode, CursorOptions};
-use bevy::input::mouse::MouseButton;
+use crate::input::{ActionInput, InputAction, InputBindings};
+use crate::origin::recenter_origin_system;
+use crate::query::TerrainQuery;
+
+/// Moves the camera with a [`CameraController`] from keyboard, mouse and gamepad input.
+///
+/// Ground queries come from the [`TerrainPlugin`](crate::TerrainPlugin), which must be added too.
+/// Bindings default to [`InputBindings::default`] unless the resource is inserted beforehand.
+pub struct CameraControllerPlugin;
+
+impl Plugin for CameraControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputBindings>().add_systems(
+            Update,
+            (
+                toggle_cursor,
+                toggle_camera_mode.before(camera_movement),
+                scroll_move_speed.before(camera_movement),
+                orbit_camera
+                    .after(toggle_camera_mode)
+                    .before(recenter_origin_system),
+                camera_movement.before(recenter_origin_system),
+            )
+                .in_set(CameraControllerSystems),
+        );
+    }
+}
+
+/// Systems of the [`CameraControllerPlugin`], for apps to pause the controller, e.g. while a menu
+/// is open.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraControllerSystems;
+
+/// The camera the terrain is streamed, simplified and culled around.
+#[derive(Component)]
+pub struct MainCamera;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    /// Free flight in the direction the camera is facing.
+    Fly,
+    /// Walking on the terrain surface under gravity.
+    Walk,
+    /// Turning around a pivot on the terrain, which stays in the centre of the view.
+    Orbit,
+}
 
 #[derive(Component)]
 pub struct CameraController {
+    pub mode: CameraMode,
+    /// Fly speed in metres per second, adjusted with the mouse wheel.
     pub move_speed: f32,
     pub look_speed: f32,
+    /// Scale the fly speed with the height above the ground, like mapping applications do.
+    pub adaptive_speed: bool,
+    /// Height above the ground at which an adaptive fly speed equals [`Self::move_speed`].
+    pub adaptive_reference_altitude: f32,
+    pub min_adaptive_speed: f32,
+    pub max_adaptive_speed: f32,
+    /// Speed multiplier while [`InputAction::Boost`] is held.
+    pub boost_multiplier: f32,
+    /// Speed multiplier while [`InputAction::Crawl`] is held.
+    pub crawl_multiplier: f32,
+    /// How quickly the fly velocity follows the input, per second. Zero makes it instantaneous.
+    pub damping: f32,
+    pub velocity: Vec3,
+    /// Minimum height of the camera above the ground in fly mode.
+    pub ground_clearance: f32,
+    /// Horizontal speed, in metres per second, in walk mode.
+    pub walk_speed: f32,
+    /// Height of the eye above the ground in walk mode.
+    pub eye_height: f32,
+    pub gravity: f32,
+    /// Vertical speed given by a jump.
+    pub jump_speed: f32,
+    /// Steepest slope, in radians, that can be walked up.
+    pub max_slope: f32,
+    pub vertical_velocity: f32,
+    pub grounded: bool,
+    /// Distance from the camera to the orbit pivot.
+    pub orbit_distance: f32,
+    /// Stick deflection, from 0 to 1, below which gamepad sticks are ignored.
+    pub gamepad_dead_zone: f32,
+    /// Turn rate, in radians per second, at full right stick deflection.
+    pub gamepad_look_speed: f32,
 }
 
 impl Default for CameraController {
     fn default() -> Self {
         Self {
+            mode: CameraMode::Fly,
             move_speed: 100.0,
             look_speed: 0.002,
+            adaptive_speed: false,
+            adaptive_reference_altitude: 100.0,
+            min_adaptive_speed: 5.0,
+            max_adaptive_speed: 2000.0,
+            boost_multiplier: 4.0,
+            crawl_multiplier: 0.25,
+            damping: 8.0,
+            velocity: Vec3::ZERO,
+            ground_clearance: 2.0,
+            walk_speed: 6.0,
+            eye_height: 1.8,
+            gravity: 9.81,
+            jump_speed: 5.0,
+            max_slope: 40.0_f32.to_radians(),
+            vertical_velocity: 0.0,
+            grounded: false,
+            orbit_distance: DEFAULT_ORBIT_DISTANCE,
+            gamepad_dead_zone: 0.15,
+            gamepad_look_speed: 2.5,
+        }
+    }
+}
+
+impl CameraController {
+    /// Returns the fly speed, before modifiers, at the given height above the ground.
+    pub fn fly_speed(&self, altitude: Option<f32>) -> f32 {
+        match altitude {
+            Some(altitude) if self.adaptive_speed => {
+                let scaled = self.move_speed * altitude.max(0.0) / self.adaptive_reference_altitude;
+                scaled.clamp(self.min_adaptive_speed, self.max_adaptive_speed)
+            }
+            _ => self.move_speed,
+        }
+    }
+
+    /// Returns the factor the fly and walk speeds are scaled by for the held speed modifiers.
+    pub fn speed_multiplier(&self, input: &ActionInput) -> f32 {
+        let mut multiplier = 1.0;
+        if input.pressed(InputAction::Boost) {
+            multiplier *= self.boost_multiplier;
         }
+        if input.pressed(InputAction::Crawl) {
+            multiplier *= self.crawl_multiplier;
+        }
+        multiplier
     }
 }
 
 pub fn toggle_cursor(
     mut cursor_options: Single<&mut CursorOptions>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mouse_input: Res<ButtonInput<MouseButton>>,
+    input: ActionInput,
+    query: Query<&CameraController>,
 ) {
-    // ESC to release mouse capture
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+    // Orbiting is driven by dragging, so clicks must not capture the cursor
+    if query.single().is_ok_and(|controller| controller.mode == CameraMode::Orbit) {
+        return;
+    }
+
+    // Release the mouse capture
+    if input.just_pressed(InputAction::ReleaseCursor) {
         cursor_options.visible = true;
         cursor_options.grab_mode = CursorGrabMode::None;
     }
     
-    // Left mouse click to capture mouse
-    if mouse_input.just_pressed(MouseButton::Left) && cursor_options.grab_mode == CursorGrabMode::None {
+    // Capture the mouse again, e.g. on a click into the window
+    if input.just_pressed(InputAction::CaptureCursor) && cursor_options.grab_mode == CursorGrabMode::None {
         cursor_options.visible = false;
         cursor_options.grab_mode = CursorGrabMode::Locked;
     }
 }
 
+/// Distance of the orbit pivot when there is no terrain in the centre of the view.
+const DEFAULT_ORBIT_DISTANCE: f32 = 500.0;
+
+/// Distance up to which the orbit pivot is picked on the terrain.
+const MAX_ORBIT_PIVOT_DISTANCE: f32 = 10000.0;
+
+const MIN_ORBIT_DISTANCE: f32 = 5.0;
+const MAX_ORBIT_DISTANCE: f32 = 20000.0;
+
+/// Steepest orbit pitch, short of looking straight up or down where the yaw is undefined.
+const MAX_ORBIT_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// Pan distance per pixel of mouse motion, relative to the orbit distance.
+const ORBIT_PAN_SPEED: f32 = 0.001;
+
+/// Switches the camera mode. [`InputAction::ToggleWalk`] toggles walking and drops the camera onto
+/// the ground, [`InputAction::ToggleOrbit`] toggles orbiting around the terrain point in the centre
+/// of the view.
+///
+/// The view itself never changes when entering or leaving orbit mode.
+pub fn toggle_camera_mode(
+    input: ActionInput,
+    mut query: Query<(&mut Transform, &mut CameraController)>,
+    mut cursor_options: Single<&mut CursorOptions>,
+    terrain_query: TerrainQuery,
+) {
+    let Ok((mut transform, mut controller)) = query.single_mut() else {
+        return;
+    };
+
+    let toggle = |mode: CameraMode| {
+        if controller.mode == mode {
+            CameraMode::Fly
+        } else {
+            mode
+        }
+    };
+    let mode = if input.just_pressed(InputAction::ToggleWalk) {
+        toggle(CameraMode::Walk)
+    } else if input.just_pressed(InputAction::ToggleOrbit) {
+        toggle(CameraMode::Orbit)
+    } else {
+        return;
+    };
+
+    match mode {
+        CameraMode::Fly => {}
+        CameraMode::Walk => {
+            let position = transform.translation;
+            let Some(ground) = terrain_query.height_at(position.x, position.z) else {
+                warn!("Cannot walk outside the terrain");
+                return;
+            };
+
+            transform.translation.y = ground + controller.eye_height;
+            controller.vertical_velocity = 0.0;
+            controller.grounded = true;
+        }
+        CameraMode::Orbit => {
+            let view_ray = Ray3d::new(transform.translation, transform.forward());
+            controller.orbit_distance = terrain_query
+                .raycast(view_ray, MAX_ORBIT_PIVOT_DISTANCE)
+                .map_or(DEFAULT_ORBIT_DISTANCE, |hit| hit.distance)
+                .max(MIN_ORBIT_DISTANCE);
+        }
+    }
+
+    // Free the cursor for dragging while orbiting, and capture it again afterwards.
+    if mode == CameraMode::Orbit || controller.mode == CameraMode::Orbit {
+        let orbit = mode == CameraMode::Orbit;
+        cursor_options.visible = orbit;
+        cursor_options.grab_mode = if orbit {
+            CursorGrabMode::None
+        } else {
+            CursorGrabMode::Locked
+        };
+    }
+
+    controller.mode = mode;
+    controller.velocity = Vec3::ZERO;
+}
+
+/// Rotates an orbiting camera around its pivot while dragging with [`InputAction::OrbitRotate`],
+/// pans while dragging with [`InputAction::OrbitPan`] and zooms along the view ray with the mouse
+/// wheel.
+pub fn orbit_camera(
+    input: ActionInput,
+    mut mouse_motion_events: MessageReader<MouseMotion>,
+    mut mouse_wheel_events: MessageReader<MouseWheel>,
+    mut query: Query<(&mut Transform, &mut CameraController)>,
+    terrain_query: TerrainQuery,
+) {
+    // Read before any early return, so that input from outside orbit mode is never applied later.
+    let motion: Vec2 = mouse_motion_events.read().map(|event| event.delta).sum();
+    let scroll: f32 = mouse_wheel_events.read().map(scroll_notches).sum();
+
+    let Ok((mut transform, mut controller)) = query.single_mut() else {
+        return;
+    };
+    if controller.mode != CameraMode::Orbit {
+        return;
+    }
+
+    let mut pivot = transform.translation + transform.forward() * controller.orbit_distance;
+
+    if input.pressed(InputAction::OrbitRotate) {
+        let forward = transform.forward();
+        let yaw = (-forward.x).atan2(-forward.z) - motion.x * controller.look_speed;
+        let pitch = (forward.y.asin() - motion.y * controller.look_speed)
+            .clamp(-MAX_ORBIT_PITCH, MAX_ORBIT_PITCH);
+        transform.rotation = Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch);
+    }
+
+    if input.pressed(InputAction::OrbitPan) {
+        // Drag the terrain along with the cursor
+        let pan = *transform.up() * motion.y - *transform.right() * motion.x;
+        pivot += pan * controller.orbit_distance * ORBIT_PAN_SPEED;
+    }
+
+    controller.orbit_distance = (controller.orbit_distance / SCROLL_SPEED_FACTOR.powf(scroll))
+        .clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+    transform.translation = pivot - transform.forward() * controller.orbit_distance;
+
+    // Rise above the ground rather than orbiting through it, still looking at the pivot.
+    let position = transform.translation;
+    if let Some(ground) = terrain_query.height_at(position.x, position.z)
+        && position.y < ground + controller.ground_clearance
+    {
+        transform.translation.y = ground + controller.ground_clearance;
+        transform.look_at(pivot, Vec3::Y);
+        controller.orbit_distance = transform.translation.distance(pivot);
+    }
+}
+
+/// Range of fly speeds reachable with the mouse wheel.
+const MIN_MOVE_SPEED: f32 = 1.0;
+const MAX_MOVE_SPEED: f32 = 5000.0;
+
+/// Factor applied to the fly speed per mouse wheel notch.
+const SCROLL_SPEED_FACTOR: f32 = 1.2;
+
+/// Scales the fly speed with the mouse wheel.
+pub fn scroll_move_speed(
+    mut mouse_wheel_events: MessageReader<MouseWheel>,
+    mut query: Query<&mut CameraController>,
+) {
+    // Drop the scrolling of frames that are skipped, rather than applying it after a mode change.
+    let Ok(mut controller) = query.single_mut() else {
+        mouse_wheel_events.clear();
+        return;
+    };
+    if controller.mode == CameraMode::Orbit {
+        mouse_wheel_events.clear();
+        return;
+    }
+
+    for event in mouse_wheel_events.read() {
+        let notches = scroll_notches(event);
+        controller.move_speed = (controller.move_speed * SCROLL_SPEED_FACTOR.powf(notches))
+            .clamp(MIN_MOVE_SPEED, MAX_MOVE_SPEED);
+    }
+}
+
+fn scroll_notches(event: &MouseWheel) -> f32 {
+    match event.unit {
+        MouseScrollUnit::Line => event.y,
+        MouseScrollUnit::Pixel => event.y / 100.0,
+    }
+}
+
 pub fn camera_movement(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
     mut mouse_motion_events: MessageReader<MouseMotion>,
     mut query: Query<(&mut Transform, &mut CameraController)>,
     time: Res<Time>,
     cursor_options: Single<&CursorOptions>,
+    gamepads: Query<&Gamepad>,
+    terrain_query: TerrainQuery,
 ) {
-    let Ok((mut transform, controller)) = query.single_mut() else {
+    // Drop the mouse motion of frames that are skipped, rather than applying it after a mode
+    // change or once the cursor is grabbed again.
+    let Ok((mut transform, mut controller)) = query.single_mut() else {
+        mouse_motion_events.clear();
         return;
     };
+    if controller.mode == CameraMode::Orbit {
+        mouse_motion_events.clear();
+        return;
+    }
+
+    let delta = time.delta_secs();
 
     // Only process mouse movement if cursor is grabbed
     if cursor_options.grab_mode != CursorGrabMode::None {
         for event in mouse_motion_events.read() {
-            // Apply yaw rotation (left/right) around the global Y axis
-            let yaw_rotation =
-                Quat::from_axis_angle(Vec3::Y, -event.delta.x * controller.look_speed);
-
-            // Apply pitch rotation (up/down) around the camera's local right axis
-            let right = transform.right();
-            let pitch_rotation =
-                Quat::from_axis_angle(*right, -event.delta.y * controller.look_speed);
-
-            // Combine rotations: first pitch (local), then yaw (global)
-            transform.rotation = yaw_rotation * pitch_rotation * transform.rotation;
+            let look = -event.delta * controller.look_speed;
+            rotate_view(&mut transform, look.x, look.y);
         }
+    } else {
+        mouse_motion_events.clear();
+    }
+
+    for gamepad in gamepads.iter() {
+        let look = apply_dead_zone(gamepad.right_stick(), controller.gamepad_dead_zone);
+        let look = Vec2::new(-look.x, look.y) * controller.gamepad_look_speed * delta;
+        rotate_view(&mut transform, look.x, look.y);
     }
 
     // Handle keyboard movement
     let mut movement = Vec3::ZERO;
 
-    // Get camera's forward and right vectors
-    let forward = transform.forward();
+    // Get camera's forward and right vectors, kept horizontal while walking
+    let (forward, right) = match controller.mode {
+        CameraMode::Fly | CameraMode::Orbit => (transform.forward(), transform.right()),
+        CameraMode::Walk => (
+            Dir3::new(transform.forward().with_y(0.0)).unwrap_or(Dir3::NEG_Z),
+            Dir3::new(transform.right().with_y(0.0)).unwrap_or(Dir3::X),
+        ),
+    };
+
+    movement += *forward
+        * (input.value(InputAction::MoveForward) - input.value(InputAction::MoveBackward));
+    movement +=
+        *right * (input.value(InputAction::MoveRight) - input.value(InputAction::MoveLeft));
+
+    for gamepad in gamepads.iter() {
+        let stick = apply_dead_zone(gamepad.left_stick(), controller.gamepad_dead_zone);
+        movement += *forward * stick.y + *right * stick.x;
+    }
+
+    // World up and down, only while flying since walking keeps to the ground
+    if controller.mode == CameraMode::Fly {
+        movement +=
+            Vec3::Y * (input.value(InputAction::MoveUp) - input.value(InputAction::MoveDown));
+    }
+
+    // Limit movement to unit length to prevent faster diagonal movement, keeping partial stick
+    // deflections slower
+    movement = movement.clamp_length_max(1.0);
+
+    let speed_multiplier = controller.speed_multiplier(&input);
+
+    match controller.mode {
+        CameraMode::Fly => {
+            let position = transform.translation;
+            let altitude = terrain_query
+                .height_at(position.x, position.z)
+                .map(|ground| position.y - ground);
+            let target_velocity = movement * controller.fly_speed(altitude) * speed_multiplier;
+            controller.velocity = if controller.damping > 0.0 {
+                let blend = 1.0 - (-controller.damping * delta).exp();
+                controller.velocity.lerp(target_velocity, blend)
+            } else {
+                target_velocity
+            };
+
+            let step = controller.velocity * delta;
+            fly(&mut transform, &controller, &terrain_query, step);
+        }
+        CameraMode::Walk => {
+            let jump = input.just_pressed(InputAction::Jump);
+            walk(
+                &mut transform,
+                &mut controller,
+                &terrain_query,
+                movement * speed_multiplier,
+                jump,
+                delta,
+            );
+        }
+        CameraMode::Orbit => {}
+    }
+}
+
+/// Turns the view by `yaw` around the world up axis and by `pitch` around its own right axis.
+fn rotate_view(transform: &mut Transform, yaw: f32, pitch: f32) {
+    // Apply yaw rotation (left/right) around the global Y axis
+    let yaw_rotation = Quat::from_axis_angle(Vec3::Y, yaw);
+
+    // Apply pitch rotation (up/down) around the camera's local right axis
     let right = transform.right();
+    let pitch_rotation = Quat::from_axis_angle(*right, pitch);
+
+    // Combine rotations: first pitch (local), then yaw (global)
+    transform.rotation = yaw_rotation * pitch_rotation * transform.rotation;
+}
 
-    if keyboard_input.pressed(KeyCode::KeyW) {
-        movement += *forward;
+/// Ignores stick deflections inside the dead zone and rescales the rest to start from zero.
+fn apply_dead_zone(stick: Vec2, dead_zone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= dead_zone {
+        return Vec2::ZERO;
     }
-    if keyboard_input.pressed(KeyCode::KeyS) {
-        movement -= *forward;
+
+    let scaled = ((length - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    stick * (scaled / length)
+}
+
+/// Moves a flying camera, keeping it [`CameraController::ground_clearance`] above the terrain.
+///
+/// The part of a step that would go into the ground is dropped, so the camera slides along slopes
+/// instead of stopping.
+fn fly(
+    transform: &mut Transform,
+    controller: &CameraController,
+    terrain_query: &TerrainQuery,
+    step: Vec3,
+) {
+    let mut target = transform.translation + step;
+
+    if let Some(ground) = terrain_query.height_at(target.x, target.z)
+        && target.y < ground + controller.ground_clearance
+    {
+        if let Some(normal) = terrain_query.normal_at(target.x, target.z) {
+            target = transform.translation + step - step.dot(normal).min(0.0) * normal;
+        }
+
+        // Projecting onto the tangent plane can still end up a little below a curved surface.
+        if let Some(ground) = terrain_query.height_at(target.x, target.z) {
+            target.y = target.y.max(ground + controller.ground_clearance);
+        }
     }
-    if keyboard_input.pressed(KeyCode::KeyD) {
-        movement += *right;
+
+    transform.translation = target;
+}
+
+/// Moves a walking camera along the terrain, refusing steps up slopes steeper than
+/// [`CameraController::max_slope`], into the ground while airborne, or off the terrain.
+fn walk(
+    transform: &mut Transform,
+    controller: &mut CameraController,
+    terrain_query: &TerrainQuery,
+    movement: Vec3,
+    jump: bool,
+    delta: f32,
+) {
+    let position = transform.translation;
+    let Some(ground) = terrain_query.height_at(position.x, position.z) else {
+        // The terrain is gone from under the camera, so there is nothing left to walk on.
+        controller.mode = CameraMode::Fly;
+        return;
+    };
+
+    let step = movement * controller.walk_speed * delta;
+    let max_rise = step.length() * controller.max_slope.tan();
+    let target = position + step;
+
+    if let Some(target_ground) = terrain_query.height_at(target.x, target.z)
+        && if controller.grounded {
+            target_ground - ground <= max_rise
+        } else {
+            target_ground + controller.eye_height <= position.y
+        }
+    {
+        transform.translation.x = target.x;
+        transform.translation.z = target.z;
     }
-    if keyboard_input.pressed(KeyCode::KeyA) {
-        movement -= *right;
+
+    if jump && controller.grounded {
+        controller.vertical_velocity = controller.jump_speed;
+        controller.grounded = false;
     }
 
-    // Normalize movement to prevent faster diagonal movement
-    if movement.length() > 0.0 {
-        movement = movement.normalize();
+    let position = transform.translation;
+    let Some(ground) = terrain_query.height_at(position.x, position.z) else {
+        return;
+    };
+    let eye_ground = ground + controller.eye_height;
+
+    // Stay on the ground when walking downhill instead of falling off every step.
+    if controller.grounded && position.y - eye_ground <= max_rise {
+        transform.translation.y = eye_ground;
+        return;
     }
 
-    // Apply movement
-    transform.translation += movement * controller.move_speed * time.delta_secs();
+    controller.vertical_velocity -= controller.gravity * delta;
+    transform.translation.y += controller.vertical_velocity * delta;
+
+    if transform.translation.y <= eye_ground {
+        transform.translation.y = eye_ground;
+        controller.vertical_velocity = 0.0;
+        controller.grounded = true;
+    } else {
+        controller.grounded = false;
+    }
 }
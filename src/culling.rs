@@ -0,0 +1,167 @@
+use crate::camera::MainCamera;
+use crate::origin::WorldOrigin;
+use crate::terrain::Tile;
+use bevy::camera::primitives::Aabb;
+use bevy::prelude::*;
+
+/// Number of grid quads along each side of an occluder cell.
+const OCCLUDER_CELL_QUADS: usize = 64;
+
+/// Coarse grid of minimum terrain heights, used to decide whether a chunk is hidden behind a ridge.
+///
+/// Positions are in world space, relative to the world origin rather than the render origin.
+///
+/// A cell only occludes a ray where the ray passes below the lowest point of the cell, so a ridge
+/// only hides what lies behind its lowest dip.
+#[derive(Resource)]
+pub struct HorizonOccluders {
+    origin: Vec2,
+    cell_size: f32,
+    cells_per_side: usize,
+    min_heights: Vec<f32>,
+}
+
+impl HorizonOccluders {
+    /// Builds the occluder grid from a row-major `(resolution + 1)²` grid of vertex positions.
+    pub fn from_positions(positions: &[[f32; 3]], resolution: usize) -> Self {
+        let stride = resolution + 1;
+        let cells_per_side = resolution.div_ceil(OCCLUDER_CELL_QUADS);
+        let mut min_heights = vec![f32::MAX; cells_per_side * cells_per_side];
+
+        for row in 0..=resolution {
+            let cell_row = (row / OCCLUDER_CELL_QUADS).min(cells_per_side - 1);
+            for col in 0..=resolution {
+                let cell_col = (col / OCCLUDER_CELL_QUADS).min(cells_per_side - 1);
+                let cell = &mut min_heights[cell_row * cells_per_side + cell_col];
+                *cell = cell.min(positions[row * stride + col][1]);
+            }
+        }
+
+        let [x, _, z] = positions[0];
+        Self {
+            origin: Vec2::new(x, z),
+            cell_size: OCCLUDER_CELL_QUADS as f32,
+            cells_per_side,
+            min_heights,
+        }
+    }
+
+    fn cell_index(&self, x: f32, z: f32) -> Option<usize> {
+        let local = (Vec2::new(x, z) - self.origin) / self.cell_size;
+        if local.x < 0.0 || local.y < 0.0 {
+            return None;
+        }
+
+        let (row, col) = (local.x as usize, local.y as usize);
+        if row >= self.cells_per_side || col >= self.cells_per_side {
+            return None;
+        }
+
+        Some(row * self.cells_per_side + col)
+    }
+
+    /// Returns true if the segment from `eye` to `target` is known to pass underneath the terrain.
+    pub fn is_occluded(&self, eye: Vec3, target: Vec3) -> bool {
+        let eye_cell = self.cell_index(eye.x, eye.z);
+        let target_cell = self.cell_index(target.x, target.z);
+
+        let distance = eye.xz().distance(target.xz());
+        let steps = (distance / (self.cell_size * 0.5)).ceil() as usize;
+
+        for step in 1..steps {
+            let point = eye.lerp(target, step as f32 / steps as f32);
+            let cell = self.cell_index(point.x, point.z);
+            if cell.is_none() || cell == eye_cell || cell == target_cell {
+                continue;
+            }
+
+            if point.y < self.min_heights[cell.unwrap()] {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// Points covering the top face of `aabb`, no more than `spacing` apart along either axis.
+///
+/// A chunk is only culled when the rays to all of them are blocked, so a notch in a ridge that
+/// exposes part of the chunk's edge keeps the chunk visible.
+fn top_face_samples(aabb: &Aabb, spacing: f32) -> impl Iterator<Item = Vec3> {
+    let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+    let steps_x = ((max.x - min.x) / spacing).ceil().max(1.0) as usize;
+    let steps_z = ((max.z - min.z) / spacing).ceil().max(1.0) as usize;
+
+    (0..=steps_z).flat_map(move |j| {
+        (0..=steps_x).map(move |i| {
+            Vec3::new(
+                min.x.lerp(max.x, i as f32 / steps_x as f32),
+                max.y,
+                min.z.lerp(max.z, j as f32 / steps_z as f32),
+            )
+        })
+    })
+}
+
+/// Per-frame visibility counts for the terrain chunks, as seen from the [`MainCamera`].
+#[derive(Resource, Default)]
+pub struct TerrainCullingStats {
+    pub visible_chunks: usize,
+    pub total_chunks: usize,
+    pub visible_triangles: usize,
+    pub total_triangles: usize,
+    /// Triangles the visible chunks would have without simplification.
+    pub visible_full_triangles: usize,
+}
+
+/// Hides chunks that are inside the view frustum but hidden behind a ridge, and records how many
+/// chunks and triangles survive both tests.
+///
+/// Chunks outside the frustum are left to Bevy's own frustum culling so that they keep casting
+/// shadows.
+pub fn terrain_culling_system(
+    camera_query: Query<(&GlobalTransform, &Projection), With<MainCamera>>,
+    occluders: Option<Res<HorizonOccluders>>,
+    world_origin: Res<WorldOrigin>,
+    mut tile_query: Query<(&Tile, &Aabb, &GlobalTransform, &mut Visibility)>,
+    mut stats: ResMut<TerrainCullingStats>,
+) {
+    let Ok((camera_transform, projection)) = camera_query.single() else {
+        return;
+    };
+
+    let frustum = projection.compute_frustum(camera_transform);
+    let eye = world_origin.to_world(camera_transform.translation()).as_vec3();
+
+    *stats = TerrainCullingStats::default();
+
+    for (tile, aabb, transform, mut visibility) in tile_query.iter_mut() {
+        stats.total_chunks += 1;
+        stats.total_triangles += tile.triangle_count;
+
+        let world_from_local = transform.affine();
+        let in_frustum = frustum.intersects_obb(aabb, &world_from_local, true, true);
+
+        let occluded = in_frustum
+            && occluders.as_ref().is_some_and(|occluders| {
+                top_face_samples(aabb, occluders.cell_size).all(|point| {
+                    let point = world_origin.to_world(world_from_local.transform_point3(point));
+                    occluders.is_occluded(eye, point.as_vec3())
+                })
+            });
+
+        let target = if occluded {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(target);
+
+        if in_frustum && !occluded {
+            stats.visible_chunks += 1;
+            stats.visible_triangles += tile.triangle_count;
+            stats.visible_full_triangles += tile.full_triangle_count;
+        }
+    }
+}
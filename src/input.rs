@@ -0,0 +1,474 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, Enum};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::Path;
+
+/// File, relative to the working directory, holding the user's input bindings.
+pub const DEFAULT_BINDINGS_PATH: &str = "input_bindings.ron";
+
+/// Everything the player can trigger with a key, mouse button or gamepad button.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum InputAction {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Boost,
+    Crawl,
+    Jump,
+    SaveBookmark,
+    BookmarkSlot1,
+    BookmarkSlot2,
+    BookmarkSlot3,
+    BookmarkSlot4,
+    BookmarkSlot5,
+    BookmarkSlot6,
+    BookmarkSlot7,
+    BookmarkSlot8,
+    BookmarkSlot9,
+    RecordKeyframe,
+    PlayPath,
+    ClearPath,
+    SavePath,
+    Screenshot,
+    CapturePoster,
+    OrbitRotate,
+    OrbitPan,
+    ToggleWalk,
+    ToggleOrbit,
+    ReleaseCursor,
+    CaptureCursor,
+    ToggleWireframe,
+    ToggleNormals,
+    ToggleSimplification,
+    OpenSettings,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 35] = [
+        InputAction::MoveForward,
+        InputAction::MoveBackward,
+        InputAction::MoveLeft,
+        InputAction::MoveRight,
+        InputAction::MoveUp,
+        InputAction::MoveDown,
+        InputAction::Boost,
+        InputAction::Crawl,
+        InputAction::Jump,
+        InputAction::SaveBookmark,
+        InputAction::BookmarkSlot1,
+        InputAction::BookmarkSlot2,
+        InputAction::BookmarkSlot3,
+        InputAction::BookmarkSlot4,
+        InputAction::BookmarkSlot5,
+        InputAction::BookmarkSlot6,
+        InputAction::BookmarkSlot7,
+        InputAction::BookmarkSlot8,
+        InputAction::BookmarkSlot9,
+        InputAction::RecordKeyframe,
+        InputAction::PlayPath,
+        InputAction::ClearPath,
+        InputAction::SavePath,
+        InputAction::Screenshot,
+        InputAction::CapturePoster,
+        InputAction::OrbitRotate,
+        InputAction::OrbitPan,
+        InputAction::ToggleWalk,
+        InputAction::ToggleOrbit,
+        InputAction::ReleaseCursor,
+        InputAction::CaptureCursor,
+        InputAction::ToggleWireframe,
+        InputAction::ToggleNormals,
+        InputAction::ToggleSimplification,
+        InputAction::OpenSettings,
+    ];
+
+    /// Bookmark slots 1 to 9, in order.
+    pub const BOOKMARK_SLOTS: [InputAction; 9] = [
+        InputAction::BookmarkSlot1,
+        InputAction::BookmarkSlot2,
+        InputAction::BookmarkSlot3,
+        InputAction::BookmarkSlot4,
+        InputAction::BookmarkSlot5,
+        InputAction::BookmarkSlot6,
+        InputAction::BookmarkSlot7,
+        InputAction::BookmarkSlot8,
+        InputAction::BookmarkSlot9,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InputAction::MoveForward => "Move forward",
+            InputAction::MoveBackward => "Move backward",
+            InputAction::MoveLeft => "Move left",
+            InputAction::MoveRight => "Move right",
+            InputAction::MoveUp => "Move up",
+            InputAction::MoveDown => "Move down",
+            InputAction::Boost => "Boost",
+            InputAction::Crawl => "Crawl",
+            InputAction::Jump => "Jump",
+            InputAction::SaveBookmark => "Save bookmark (hold with a slot)",
+            InputAction::BookmarkSlot1 => "Bookmark slot 1",
+            InputAction::BookmarkSlot2 => "Bookmark slot 2",
+            InputAction::BookmarkSlot3 => "Bookmark slot 3",
+            InputAction::BookmarkSlot4 => "Bookmark slot 4",
+            InputAction::BookmarkSlot5 => "Bookmark slot 5",
+            InputAction::BookmarkSlot6 => "Bookmark slot 6",
+            InputAction::BookmarkSlot7 => "Bookmark slot 7",
+            InputAction::BookmarkSlot8 => "Bookmark slot 8",
+            InputAction::BookmarkSlot9 => "Bookmark slot 9",
+            InputAction::RecordKeyframe => "Record flythrough keyframe",
+            InputAction::PlayPath => "Play/stop flythrough",
+            InputAction::ClearPath => "Clear flythrough",
+            InputAction::SavePath => "Stop recording and save flythrough",
+            InputAction::Screenshot => "Screenshot",
+            InputAction::CapturePoster => "Poster capture (8K)",
+            InputAction::OrbitRotate => "Orbit rotate (drag)",
+            InputAction::OrbitPan => "Orbit pan (drag)",
+            InputAction::ToggleWalk => "Toggle walk mode",
+            InputAction::ToggleOrbit => "Toggle orbit mode",
+            InputAction::ReleaseCursor => "Release cursor",
+            InputAction::CaptureCursor => "Capture cursor",
+            InputAction::ToggleWireframe => "Toggle wireframe",
+            InputAction::ToggleNormals => "Toggle normals",
+            InputAction::ToggleSimplification => "Toggle simplification",
+            InputAction::OpenSettings => "Open settings",
+        }
+    }
+}
+
+/// A single physical input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{}", key.variant_name()),
+            Binding::Mouse(button) => write!(f, "Mouse {}", button.variant_name()),
+            Binding::Gamepad(button) => write!(f, "Gamepad {}", button.variant_name()),
+        }
+    }
+}
+
+/// Input that cancels rebinding an action rather than being bound to it. It keeps its default
+/// binding, but [`InputBindings::rebind`] never assigns it.
+pub const CANCEL_REBINDING: Binding = Binding::Key(KeyCode::Escape);
+
+/// Maps every [`InputAction`] to the inputs that trigger it.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct InputBindings {
+    bindings: BTreeMap<InputAction, Vec<Binding>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use Binding::{Gamepad, Key, Mouse};
+
+        let bindings = InputAction::ALL
+            .into_iter()
+            .map(|action| {
+                let inputs = match action {
+                    InputAction::MoveForward => vec![Key(KeyCode::KeyW)],
+                    InputAction::MoveBackward => vec![Key(KeyCode::KeyS)],
+                    InputAction::MoveLeft => vec![Key(KeyCode::KeyA)],
+                    InputAction::MoveRight => vec![Key(KeyCode::KeyD)],
+                    InputAction::MoveUp => {
+                        vec![Key(KeyCode::KeyE), Gamepad(GamepadButton::RightTrigger2)]
+                    }
+                    InputAction::MoveDown => {
+                        vec![Key(KeyCode::KeyQ), Gamepad(GamepadButton::LeftTrigger2)]
+                    }
+                    InputAction::Boost => vec![
+                        Key(KeyCode::ShiftLeft),
+                        Key(KeyCode::ShiftRight),
+                        Gamepad(GamepadButton::RightTrigger),
+                    ],
+                    InputAction::Crawl => vec![
+                        Key(KeyCode::AltLeft),
+                        Key(KeyCode::AltRight),
+                        Gamepad(GamepadButton::LeftTrigger),
+                    ],
+                    InputAction::Jump => {
+                        vec![Key(KeyCode::Space), Gamepad(GamepadButton::South)]
+                    }
+                    InputAction::SaveBookmark => {
+                        vec![Key(KeyCode::ControlLeft), Key(KeyCode::ControlRight)]
+                    }
+                    InputAction::BookmarkSlot1 => vec![Key(KeyCode::Digit1)],
+                    InputAction::BookmarkSlot2 => vec![Key(KeyCode::Digit2)],
+                    InputAction::BookmarkSlot3 => vec![Key(KeyCode::Digit3)],
+                    InputAction::BookmarkSlot4 => vec![Key(KeyCode::Digit4)],
+                    InputAction::BookmarkSlot5 => vec![Key(KeyCode::Digit5)],
+                    InputAction::BookmarkSlot6 => vec![Key(KeyCode::Digit6)],
+                    InputAction::BookmarkSlot7 => vec![Key(KeyCode::Digit7)],
+                    InputAction::BookmarkSlot8 => vec![Key(KeyCode::Digit8)],
+                    InputAction::BookmarkSlot9 => vec![Key(KeyCode::Digit9)],
+                    InputAction::RecordKeyframe => vec![Key(KeyCode::KeyK)],
+                    InputAction::PlayPath => vec![Key(KeyCode::KeyP)],
+                    InputAction::ClearPath => vec![Key(KeyCode::Backspace)],
+                    InputAction::SavePath => vec![Key(KeyCode::Enter)],
+                    InputAction::Screenshot => vec![Key(KeyCode::F12)],
+                    InputAction::CapturePoster => vec![Key(KeyCode::F9)],
+                    InputAction::OrbitRotate => vec![Mouse(MouseButton::Left)],
+                    InputAction::OrbitPan => vec![Mouse(MouseButton::Middle)],
+                    InputAction::ToggleWalk => vec![Key(KeyCode::KeyG)],
+                    InputAction::ToggleOrbit => vec![Key(KeyCode::KeyO)],
+                    InputAction::ReleaseCursor => vec![Key(KeyCode::Escape)],
+                    InputAction::CaptureCursor => vec![Mouse(MouseButton::Left)],
+                    InputAction::ToggleWireframe => vec![Key(KeyCode::KeyM)],
+                    InputAction::ToggleNormals => vec![Key(KeyCode::KeyN)],
+                    InputAction::ToggleSimplification => vec![Key(KeyCode::KeyL)],
+                    InputAction::OpenSettings => vec![Key(KeyCode::F1)],
+                };
+                (action, inputs)
+            })
+            .collect();
+
+        Self { bindings }
+    }
+}
+
+impl InputBindings {
+    pub fn get(&self, action: InputAction) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set(&mut self, action: InputAction, bindings: Vec<Binding>) {
+        self.bindings.insert(action, bindings);
+    }
+
+    /// Binds `action` to `binding` in place of its inputs of the same kind, so that rebinding a
+    /// key keeps the action's mouse and gamepad buttons.
+    ///
+    /// Returns `false`, leaving the bindings unchanged, when `binding` is [`CANCEL_REBINDING`].
+    pub fn rebind(&mut self, action: InputAction, binding: Binding) -> bool {
+        if binding == CANCEL_REBINDING {
+            return false;
+        }
+
+        let inputs = self.bindings.entry(action).or_default();
+        let same_kind = |input: &Binding| mem::discriminant(input) == mem::discriminant(&binding);
+
+        let index = inputs.iter().position(same_kind).unwrap_or(inputs.len());
+        inputs.retain(|input| !same_kind(input));
+        inputs.insert(index.min(inputs.len()), binding);
+        true
+    }
+
+    /// Reads bindings from a RON file. Actions missing from the file keep their default bindings.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let file: BindingsFile = ron::from_str(&text)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        let mut bindings = Self::default();
+        for (action, inputs) in file.bindings {
+            let inputs = inputs
+                .iter()
+                .filter_map(|input| {
+                    let binding = input.to_binding();
+                    if binding.is_none() {
+                        warn!("Ignoring unknown input {input:?} bound to {action:?}");
+                    }
+                    binding
+                })
+                .collect();
+            bindings.set(action, inputs);
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = BindingsFile {
+            bindings: self
+                .bindings
+                .iter()
+                .map(|(action, inputs)| (*action, inputs.iter().map(BindingName::from).collect()))
+                .collect(),
+        };
+
+        let text = ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+}
+
+/// Loads the bindings from [`DEFAULT_BINDINGS_PATH`], falling back to the defaults.
+pub fn load_input_bindings() -> InputBindings {
+    match InputBindings::load(DEFAULT_BINDINGS_PATH) {
+        Ok(bindings) => bindings,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => InputBindings::default(),
+        Err(error) => {
+            warn!("Failed to load {DEFAULT_BINDINGS_PATH}, using default bindings: {error}");
+            InputBindings::default()
+        }
+    }
+}
+
+/// On-disk form of [`InputBindings`], naming inputs by their enum variant, e.g. `Key("KeyW")`.
+#[derive(Serialize, Deserialize)]
+struct BindingsFile {
+    bindings: BTreeMap<InputAction, Vec<BindingName>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum BindingName {
+    Key(String),
+    Mouse(String),
+    Gamepad(String),
+}
+
+impl From<&Binding> for BindingName {
+    fn from(binding: &Binding) -> Self {
+        match binding {
+            Binding::Key(key) => BindingName::Key(key.variant_name().to_string()),
+            Binding::Mouse(button) => BindingName::Mouse(button.variant_name().to_string()),
+            Binding::Gamepad(button) => BindingName::Gamepad(button.variant_name().to_string()),
+        }
+    }
+}
+
+impl BindingName {
+    fn to_binding(&self) -> Option<Binding> {
+        match self {
+            BindingName::Key(name) => unit_variant(name).map(Binding::Key),
+            BindingName::Mouse(name) => unit_variant(name).map(Binding::Mouse),
+            BindingName::Gamepad(name) => unit_variant(name).map(Binding::Gamepad),
+        }
+    }
+}
+
+/// Looks up a field-less enum variant by name through reflection.
+fn unit_variant<T: FromReflect>(name: &str) -> Option<T> {
+    T::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+/// Reads the state of [`InputAction`]s through the current [`InputBindings`].
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    bindings: Res<'w, InputBindings>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse_buttons: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.bindings.get(action).iter().any(|binding| match binding {
+            Binding::Key(key) => self.keys.pressed(*key),
+            Binding::Mouse(button) => self.mouse_buttons.pressed(*button),
+            Binding::Gamepad(button) => self.gamepads.iter().any(|gamepad| gamepad.pressed(*button)),
+        })
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.bindings.get(action).iter().any(|binding| match binding {
+            Binding::Key(key) => self.keys.just_pressed(*key),
+            Binding::Mouse(button) => self.mouse_buttons.just_pressed(*button),
+            Binding::Gamepad(button) => self
+                .gamepads
+                .iter()
+                .any(|gamepad| gamepad.just_pressed(*button)),
+        })
+    }
+
+    /// Returns how far an action is pressed, from 0 to 1, using the analog value of gamepad
+    /// triggers.
+    pub fn value(&self, action: InputAction) -> f32 {
+        self.bindings
+            .get(action)
+            .iter()
+            .map(|binding| match binding {
+                Binding::Key(key) => self.keys.pressed(*key) as u8 as f32,
+                Binding::Mouse(button) => self.mouse_buttons.pressed(*button) as u8 as f32,
+                Binding::Gamepad(button) => self
+                    .gamepads
+                    .iter()
+                    .filter_map(|gamepad| gamepad.get(*button))
+                    .fold(0.0, f32::max),
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_keys_trigger_one_action_each() {
+        let bindings = InputBindings::default();
+        let mut actions_by_key: BTreeMap<String, Vec<InputAction>> = BTreeMap::new();
+        for action in InputAction::ALL {
+            for binding in bindings.get(action) {
+                if let Binding::Key(_) | Binding::Gamepad(_) = binding {
+                    actions_by_key
+                        .entry(binding.to_string())
+                        .or_default()
+                        .push(action);
+                }
+            }
+        }
+
+        for (key, actions) in actions_by_key {
+            assert_eq!(actions.len(), 1, "{key} is bound to {actions:?}");
+        }
+    }
+
+    #[test]
+    fn rebinding_keeps_other_kinds_of_input() {
+        let mut bindings = InputBindings::default();
+        bindings.rebind(InputAction::Boost, Binding::Key(KeyCode::KeyB));
+
+        assert_eq!(
+            bindings.get(InputAction::Boost),
+            [
+                Binding::Key(KeyCode::KeyB),
+                Binding::Gamepad(GamepadButton::RightTrigger)
+            ]
+        );
+
+        bindings.rebind(InputAction::Boost, Binding::Gamepad(GamepadButton::North));
+        assert_eq!(
+            bindings.get(InputAction::Boost),
+            [
+                Binding::Key(KeyCode::KeyB),
+                Binding::Gamepad(GamepadButton::North)
+            ]
+        );
+
+        bindings.rebind(InputAction::ToggleWalk, Binding::Mouse(MouseButton::Back));
+        assert_eq!(
+            bindings.get(InputAction::ToggleWalk),
+            [Binding::Key(KeyCode::KeyG), Binding::Mouse(MouseButton::Back)]
+        );
+    }
+
+    #[test]
+    fn cancelling_a_rebind_keeps_the_bindings() {
+        let mut bindings = InputBindings::default();
+        assert!(!bindings.rebind(InputAction::Jump, CANCEL_REBINDING));
+        assert!(!bindings.rebind(InputAction::ReleaseCursor, CANCEL_REBINDING));
+        assert_eq!(bindings, InputBindings::default());
+
+        assert!(bindings.rebind(InputAction::Jump, Binding::Key(KeyCode::KeyJ)));
+        assert_eq!(
+            bindings.get(InputAction::Jump),
+            [Binding::Key(KeyCode::KeyJ), Binding::Gamepad(GamepadButton::South)]
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! Procedural terrain for Bevy: generation, chunk streaming and culling, ground queries and a
+//! camera controller that knows about the ground.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use terrain::{
+//!     CameraController, CameraControllerPlugin, MainCamera, TerrainPlugin, TerrainSettings,
+//! };
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(TerrainPlugin::new(TerrainSettings {
+//!         seed: 42,
+//!         ..default()
+//!     }))
+//!     .add_plugins(CameraControllerPlugin)
+//!     .add_systems(Startup, |mut commands: Commands| {
+//!         commands.spawn((
+//!             Camera3d::default(),
+//!             CameraController::default(),
+//!             Transform::from_xyz(0.0, 500.0, 0.0),
+//!             MainCamera,
+//!         ));
+//!     })
+//!     .run();
+//! ```
+
+pub mod cache;
+pub mod camera;
+pub mod culling;
+pub mod input;
+pub mod noise;
+pub mod origin;
+pub mod progress;
+pub mod query;
+pub mod rtin;
+pub mod terrain;
+
+pub use camera::{CameraController, CameraControllerPlugin, CameraControllerSystems, MainCamera};
+pub use query::TerrainQuery;
+pub use terrain::{TerrainPlugin, TerrainSettings};
@@ -0,0 +1,461 @@
+use bevy::math::{DMat2, DVec2};
+use bevy::prelude::*;
+use wide::bytemuck::cast;
+use wide::{f32x8, i32x8, u32x8};
+
+fn smoothstep(x: f32) -> f32 {
+    let x = x.clamp(0.0, 1.0);
+    x * x * (3.0 - 2.0 * x)
+}
+
+pub fn smoothstep_bounds(low: f32, high: f32, x: f32) -> f32 {
+    let t = ((x - low) / (high - low)).clamp(0.0, 1.0);
+    smoothstep(t)
+}
+
+fn noise(t: Vec2, seed: u32) -> (f32, Vec2) // value, dx, dy
+{
+    let p = t.floor();
+    // Step to the neighbouring cells in integers: above 2^24, `p + 1.0` rounds back to `p`.
+    let (ix, iy) = (p.x as i32, p.y as i32);
+
+    let a = hash_cell(ix, iy, seed);
+    let b = hash_cell(ix.wrapping_add(1), iy, seed);
+    let c = hash_cell(ix, iy.wrapping_add(1), seed);
+    let d = hash_cell(ix.wrapping_add(1), iy.wrapping_add(1), seed);
+
+    let k0 = a;
+    let k1 = b - a;
+    let k2 = c - a;
+    let k3 = a - b - c + d;
+
+    let w = t.fract_gl();
+    let (sx, sz) = (smoothstep(w.x), smoothstep(w.y));
+
+    let value = k0 + k1 * sx + k2 * sz + k3 * sx * sz;
+
+    let ds = 6.0 * w * (1.0 - w);
+    let dx = ds.x * (k1 + k3 * sz);
+    let dy = ds.y * (k2 + k3 * sx);
+
+    (value, Vec2::new(dx, dy))
+}
+
+const ROTATION: Mat2 = Mat2::from_cols_array(&[0.8, 0.6, -0.6, 0.8]);
+const ROTATION_TRANSPOSE: Mat2 = Mat2::from_cols_array(&[0.8, -0.6, 0.6, 0.8]);
+
+/// Number of noise octaves summed by [`fbm`] and its variants.
+const OCTAVES: usize = 11;
+
+pub fn fbm(point: Vec2, seed: u32) -> (f32, Vec2) // value, dx, dy
+{
+    fbm_octaves(point, seed, OCTAVES)
+}
+
+fn fbm_octaves(point: Vec2, seed: u32, octaves: usize) -> (f32, Vec2) // value, dx, dy
+{
+    let scale_factor = 2.0;
+
+    let mut p = point;
+    let mut scale = 1.0;
+
+    let mut rotation = Mat2::IDENTITY;
+
+    let mut value = 0.0;
+    let mut derivative = Vec2::new(0.0, 0.0);
+
+    for _ in 0..octaves {
+        let (noise, noise_derivative) = noise(p, seed);
+
+        value += scale * noise;
+        derivative += scale * rotation * noise_derivative;
+
+        scale /= scale_factor;
+
+        p = scale_factor * ROTATION * p;
+        rotation = scale_factor * ROTATION_TRANSPOSE * rotation;
+    }
+
+    (value, derivative)
+}
+
+/// [`fbm`] evaluated in double precision, for points whose coordinates are too large for `f32`
+/// to resolve the finest octaves.
+pub fn fbm_f64(point: DVec2, seed: u32) -> (f64, DVec2) // value, dx, dy
+{
+    fbm_f64_octaves(point, seed, OCTAVES)
+}
+
+fn fbm_f64_octaves(point: DVec2, seed: u32, octaves: usize) -> (f64, DVec2) // value, dx, dy
+{
+    let scale_factor = 2.0;
+
+    let mut p = point;
+    let mut scale = 1.0;
+
+    let mut rotation = DMat2::IDENTITY;
+
+    let mut value = 0.0;
+    let mut derivative = DVec2::new(0.0, 0.0);
+
+    for _ in 0..octaves {
+        let (noise, noise_derivative) = noise_f64(p, seed);
+
+        value += scale * noise;
+        derivative += scale * rotation * noise_derivative;
+
+        scale /= scale_factor;
+
+        p = scale_factor * ROTATION.as_dmat2() * p;
+        rotation = scale_factor * ROTATION_TRANSPOSE.as_dmat2() * rotation;
+    }
+
+    (value, derivative)
+}
+
+fn noise_f64(t: DVec2, seed: u32) -> (f64, DVec2) // value, dx, dy
+{
+    let p = t.floor();
+    // Wrap rather than saturate far outside the i32 range, so that the noise keeps varying.
+    let (ix, iy) = (p.x as i64 as i32, p.y as i64 as i32);
+
+    let a = hash_cell(ix, iy, seed) as f64;
+    let b = hash_cell(ix.wrapping_add(1), iy, seed) as f64;
+    let c = hash_cell(ix, iy.wrapping_add(1), seed) as f64;
+    let d = hash_cell(ix.wrapping_add(1), iy.wrapping_add(1), seed) as f64;
+
+    let k0 = a;
+    let k1 = b - a;
+    let k2 = c - a;
+    let k3 = a - b - c + d;
+
+    let w = t - p;
+    let smooth = |x: f64| x * x * (3.0 - 2.0 * x);
+    let (sx, sz) = (smooth(w.x), smooth(w.y));
+
+    let value = k0 + k1 * sx + k2 * sz + k3 * sx * sz;
+
+    let ds = 6.0 * w * (1.0 - w);
+    let dx = ds.x * (k1 + k3 * sz);
+    let dy = ds.y * (k2 + k3 * sx);
+
+    (value, DVec2::new(dx, dy))
+}
+
+/// Number of points evaluated together by [`fbm_lanes`].
+pub const LANES: usize = 8;
+
+pub type Lanes = [f32; LANES];
+
+/// Evaluates [`fbm`] at [`LANES`] points at once, in `f32x8` SIMD vectors.
+///
+/// Every lane performs exactly the same floating-point operations, in the same order, as the scalar
+/// path, so the results are bit-identical as long as the noise coordinates of every octave stay
+/// within `i32` range. The finest octave is scaled by `2^(OCTAVES - 1)`, which bounds the distance
+/// of the input points from the origin to about `2^31 / 1024`, a little over two million.
+pub fn fbm_lanes(x: &Lanes, y: &Lanes, seed: u32) -> (Lanes, Lanes, Lanes) // value, dx, dy
+{
+    let scale_factor = 2.0;
+    let octave_rotation = scale_factor * ROTATION;
+
+    let mut px = f32x8::new(*x);
+    let mut py = f32x8::new(*y);
+    let mut scale = 1.0;
+
+    let mut rotation = Mat2::IDENTITY;
+
+    let mut value = f32x8::ZERO;
+    let mut dx = f32x8::ZERO;
+    let mut dy = f32x8::ZERO;
+
+    for _ in 0..OCTAVES {
+        let (noise, noise_dx, noise_dy) = noise_lanes(px, py, seed);
+        let scaled_rotation = scale * rotation;
+
+        value += f32x8::splat(scale) * noise;
+        dx += f32x8::splat(scaled_rotation.x_axis.x) * noise_dx
+            + f32x8::splat(scaled_rotation.y_axis.x) * noise_dy;
+        dy += f32x8::splat(scaled_rotation.x_axis.y) * noise_dx
+            + f32x8::splat(scaled_rotation.y_axis.y) * noise_dy;
+
+        scale /= scale_factor;
+
+        (px, py) = (
+            f32x8::splat(octave_rotation.x_axis.x) * px
+                + f32x8::splat(octave_rotation.y_axis.x) * py,
+            f32x8::splat(octave_rotation.x_axis.y) * px
+                + f32x8::splat(octave_rotation.y_axis.y) * py,
+        );
+        rotation = scale_factor * ROTATION_TRANSPOSE * rotation;
+    }
+
+    (value.to_array(), dx.to_array(), dy.to_array())
+}
+
+fn noise_lanes(x: f32x8, y: f32x8, seed: u32) -> (f32x8, f32x8, f32x8) // value, dx, dy
+{
+    let px = floor_lanes(x);
+    let py = floor_lanes(y);
+    let (ix, iy) = (px.trunc_int(), py.trunc_int());
+    let one = i32x8::splat(1);
+
+    // Integer vector addition wraps like `wrapping_add` in the scalar path.
+    let a = hash_cell_lanes(ix, iy, seed);
+    let b = hash_cell_lanes(ix + one, iy, seed);
+    let c = hash_cell_lanes(ix, iy + one, seed);
+    let d = hash_cell_lanes(ix + one, iy + one, seed);
+
+    let k0 = a;
+    let k1 = b - a;
+    let k2 = c - a;
+    let k3 = a - b - c + d;
+
+    let wx = x - px;
+    let wy = y - py;
+    let (sx, sz) = (smoothstep_lanes(wx), smoothstep_lanes(wy));
+
+    let value = k0 + k1 * sx + k2 * sz + k3 * sx * sz;
+
+    let six = f32x8::splat(6.0);
+    let dsx = six * wx * (f32x8::ONE - wx);
+    let dsy = six * wy * (f32x8::ONE - wy);
+    let dx = dsx * (k1 + k3 * sz);
+    let dy = dsy * (k2 + k3 * sx);
+
+    (value, dx, dy)
+}
+
+fn smoothstep_lanes(x: f32x8) -> f32x8 {
+    let x = x.max(f32x8::ZERO).min(f32x8::ONE);
+    x * x * (f32x8::splat(3.0) - f32x8::splat(2.0) * x)
+}
+
+/// [`f32::floor`] built from a truncating conversion, which needs neither SSE4.1 nor AVX. Matches
+/// it exactly, including for `-0.0`, for all values within `i32` range.
+fn floor_lanes(x: f32x8) -> f32x8 {
+    let truncated = x.trunc_int().round_float();
+    let floored = truncated.simd_gt(x).select(truncated - f32x8::ONE, truncated);
+    truncated.simd_eq(x).select(x, floored)
+}
+
+/// [`hash_cell`] of eight cells at once.
+fn hash_cell_lanes(ix: i32x8, iy: i32x8, seed: u32) -> f32x8 {
+    let (ix, iy): (u32x8, u32x8) = (cast(ix), cast(iy));
+    let mut h = u32x8::splat(seed)
+        + ix * u32x8::splat(374761393)
+        + iy * u32x8::splat(668265263);
+
+    h ^= h >> 13;
+    h *= u32x8::splat(1274126177);
+
+    // There is no unsigned conversion to float before AVX-512, so convert the two halves, which
+    // are exact in `f32`, and let their sum round once, exactly like `h as f32`.
+    let high: i32x8 = cast(h >> 16);
+    let low: i32x8 = cast(h & u32x8::splat(0xffff));
+    let h = high.round_float() * f32x8::splat(65536.0) + low.round_float();
+
+    h * f32x8::splat(1.0 / 4294967296.0)
+}
+
+fn hash_cell(ix: i32, iy: i32, seed: u32) -> f32 {
+    let mut h = seed
+        .wrapping_add((ix as u32).wrapping_mul(374761393))
+        .wrapping_add((iy as u32).wrapping_mul(668265263));
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(1274126177);
+
+    (h as f32) * (1.0 / 4294967296.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u32 = 3266489917;
+
+    // Golden values: any change to these means every generated world changes too.
+
+    #[test]
+    fn hash_known_values() {
+        assert_eq!(hash_cell(0, 0, 1), 0.29665563);
+        assert_eq!(hash_cell(12, -4, SEED), 0.086935684);
+        assert_eq!(hash_cell(-1001, 42, 7), 0.32121873);
+    }
+
+    #[test]
+    fn noise_known_values() {
+        assert_eq!(
+            noise(Vec2::new(0.25, 0.75), SEED),
+            (0.553544, Vec2::new(-0.5145562, -0.36445725))
+        );
+        assert_eq!(
+            noise(Vec2::new(-3.6, 12.1), SEED),
+            (0.6291878, Vec2::new(-0.08909833, -0.19395897))
+        );
+        assert_eq!(
+            noise(Vec2::new(100.3, -57.9), 7),
+            (0.26638412, Vec2::new(-0.2796929, -0.020688636))
+        );
+    }
+
+    #[test]
+    fn fbm_known_values() {
+        assert_eq!(
+            fbm(Vec2::new(0.1, 0.2), SEED),
+            (1.5171983, Vec2::new(-1.04087, 0.4981853))
+        );
+        assert_eq!(
+            fbm(Vec2::new(-1.37, 2.9), SEED),
+            (0.53592277, Vec2::new(-1.685853, 0.91144747))
+        );
+        assert_eq!(
+            fbm(Vec2::new(25.0, -40.5), 7),
+            (0.94722843, Vec2::new(-2.3756435, 1.3614249))
+        );
+    }
+
+    /// Pseudo-random points in `[-range, range)²`, exactly representable as `f32`, drawn from the
+    /// noise hash itself.
+    fn random_points(count: usize, range: f64) -> impl Iterator<Item = DVec2> {
+        (0..count as i32).map(move |i| {
+            let unit = Vec2::new(hash_cell(i, 0, 0x5eed), hash_cell(i, 1, 0x5eed));
+            ((unit * 2.0 - 1.0) * range as f32).as_dvec2()
+        })
+    }
+
+    /// A noise function with an analytic gradient, evaluated at `f64` coordinates.
+    struct GradientCase {
+        name: String,
+        function: Box<dyn Fn(DVec2) -> (f64, DVec2)>,
+        /// Finite-difference step, a power of two so that offset points stay exact in `f32`.
+        step: f64,
+        /// Largest accepted gradient error, relative to `1 + |gradient|`.
+        tolerance: f64,
+    }
+
+    /// Every noise function whose gradient is derived by hand. New noise types belong here too.
+    fn gradient_cases() -> Vec<GradientCase> {
+        let mut cases = vec![
+            GradientCase {
+                name: "noise".to_string(),
+                function: Box::new(|p| {
+                    let (value, gradient) = noise(p.as_vec2(), SEED);
+                    (value as f64, gradient.as_dvec2())
+                }),
+                step: 2f64.powi(-12),
+                tolerance: 5e-3,
+            },
+            GradientCase {
+                name: "noise_f64".to_string(),
+                function: Box::new(|p| noise_f64(p, SEED)),
+                step: 2f64.powi(-20),
+                tolerance: 1e-4,
+            },
+        ];
+
+        // The octave count changes how much the rotation bookkeeping matters.
+        for octaves in 1..=OCTAVES {
+            cases.push(GradientCase {
+                name: format!("fbm_f64 with {octaves} octaves"),
+                function: Box::new(move |p| fbm_f64_octaves(p, SEED, octaves)),
+                // Shrink the step with the finest octave's frequency.
+                step: 2f64.powi(-20 - octaves as i32),
+                tolerance: 1e-4,
+            });
+        }
+
+        cases
+    }
+
+    /// Central finite differences of the value of `function` at `point`.
+    fn finite_difference_gradient(
+        function: &dyn Fn(DVec2) -> (f64, DVec2),
+        point: DVec2,
+        step: f64,
+    ) -> DVec2 {
+        let difference = |offset: DVec2| {
+            (function(point + offset).0 - function(point - offset).0) / (2.0 * step)
+        };
+        DVec2::new(difference(DVec2::X * step), difference(DVec2::Y * step))
+    }
+
+    #[test]
+    fn gradients_match_finite_differences() {
+        for case in gradient_cases() {
+            for point in random_points(200, 50.0) {
+                let (_, analytic) = (case.function)(point);
+                let numeric = finite_difference_gradient(&case.function, point, case.step);
+
+                let error = (analytic - numeric).length() / (1.0 + numeric.length());
+                assert!(
+                    error <= case.tolerance,
+                    "{} at {point}: analytic gradient {analytic}, finite differences {numeric}",
+                    case.name
+                );
+            }
+        }
+    }
+
+    /// The single-precision fBm is too noisy for finite differences at high octave counts, so it is
+    /// checked against the double-precision gradient, which is checked above.
+    #[test]
+    fn fbm_gradient_matches_double_precision() {
+        let range = 4.0;
+        for octaves in 1..=OCTAVES {
+            // Rounding of the finest octave's coordinates dominates, and doubles with each octave.
+            let tolerance = 16.0 * f32::EPSILON as f64 * range * 2f64.powi(octaves as i32 - 1);
+
+            for point in random_points(200, range) {
+                let (_, single) = fbm_octaves(point.as_vec2(), SEED, octaves);
+                let (_, double) = fbm_f64_octaves(point, SEED, octaves);
+
+                let error = (single.as_dvec2() - double).length() / (1.0 + double.length());
+                assert!(
+                    error <= tolerance,
+                    "fbm with {octaves} octaves at {point}: {single} in f32, {double} in f64"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fbm_lanes_matches_fbm() {
+        // Rows laid out the way terrain generation samples them, around the origin, at negative
+        // coordinates, where the finest octaves pass 2^24 and float steps between cells are lost,
+        // and just inside the documented bound, where they near the end of the `i32` range.
+        let offsets = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(-0.5, 0.25),
+            Vec2::new(-37.3, -12.9),
+            Vec2::new(1234.5, -987.25),
+            Vec2::new(-2048.0, 4096.0),
+            Vec2::new(16384.0, -16384.0),
+            Vec2::new(-1.0e6, 1.4e6),
+        ];
+        let seeds = [0, 1, 7, SEED, u32::MAX];
+
+        for seed in seeds {
+            for offset in offsets {
+                for row in 0..4 {
+                    let y = offset.y + row as f32 / 8.0;
+                    let xs: Vec<f32> = (0..256).map(|col| offset.x + col as f32 / 64.0).collect();
+
+                    for batch in xs.chunks_exact(LANES) {
+                        let batch: &Lanes = batch.try_into().unwrap();
+                        let (values, dx, dy) = fbm_lanes(batch, &[y; LANES], seed);
+                        for lane in 0..LANES {
+                            let (value, d) = fbm(Vec2::new(batch[lane], y), seed);
+                            assert_eq!(
+                                [values[lane], dx[lane], dy[lane]].map(f32::to_bits),
+                                [value, d.x, d.y].map(f32::to_bits),
+                                "seed {seed} at ({}, {y})",
+                                batch[lane]
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
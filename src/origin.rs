@@ -0,0 +1,64 @@
+use crate::camera::MainCamera;
+use crate::terrain::{NormalLines, Tile};
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+/// Horizontal distance from the render origin at which the world is shifted back under the camera.
+const RECENTER_DISTANCE: f32 = 2048.0;
+
+/// World-space position of the render origin.
+///
+/// Render transforms are `f32` and lose precision far from zero, so world positions are kept in
+/// `f64` and everything is rendered relative to this origin, which follows the [`MainCamera`].
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct WorldOrigin(pub DVec3);
+
+impl WorldOrigin {
+    pub fn to_world(self, render: Vec3) -> DVec3 {
+        self.0 + render.as_dvec3()
+    }
+
+    pub fn to_render(self, world: DVec3) -> Vec3 {
+        (world - self.0).as_vec3()
+    }
+}
+
+/// Moves the render origin under the camera once it strays more than [`RECENTER_DISTANCE`] away,
+/// shifting the camera and every terrain transform by the same amount.
+#[allow(clippy::type_complexity)]
+pub fn recenter_origin_system(
+    mut origin: ResMut<WorldOrigin>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    mut tile_query: Query<(&Tile, &mut Transform), Without<MainCamera>>,
+    mut normal_lines_query: Query<
+        &mut Transform,
+        (With<NormalLines>, Without<MainCamera>, Without<Tile>),
+    >,
+) {
+    let Ok(mut camera_transform) = camera_query.single_mut() else {
+        return;
+    };
+
+    let position = camera_transform.translation;
+    if position.xz().length() < RECENTER_DISTANCE {
+        return;
+    }
+
+    // Whole-unit shifts keep the chunk translations exactly representable.
+    let offset = Vec3::new(position.x.round(), 0.0, position.z.round());
+    camera_transform.translation -= offset;
+    origin.0 += offset.as_dvec3();
+
+    for (tile, mut transform) in tile_query.iter_mut() {
+        transform.translation = origin.to_render(tile.origin);
+    }
+
+    for mut transform in normal_lines_query.iter_mut() {
+        transform.translation = origin.to_render(DVec3::ZERO);
+    }
+
+    info!(
+        "Recentered world origin to ({:.0}, {:.0})",
+        origin.0.x, origin.0.z
+    );
+}
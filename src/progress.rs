@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationStage {
+    Sampling,
+    BuildingIndices,
+    BuildingNormals,
+    Uploading,
+}
+
+impl GenerationStage {
+    const ALL: [GenerationStage; 4] = [
+        GenerationStage::Sampling,
+        GenerationStage::BuildingIndices,
+        GenerationStage::BuildingNormals,
+        GenerationStage::Uploading,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            GenerationStage::Sampling => "Sampling terrain",
+            GenerationStage::BuildingIndices => "Building indices",
+            GenerationStage::BuildingNormals => "Building normals",
+            GenerationStage::Uploading => "Uploading meshes",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            GenerationStage::Sampling | GenerationStage::BuildingNormals => "rows",
+            GenerationStage::BuildingIndices | GenerationStage::Uploading => "chunks",
+        }
+    }
+
+    /// Rough share of the total generation time spent in this stage.
+    fn weight(self) -> f32 {
+        match self {
+            GenerationStage::Sampling => 0.85,
+            GenerationStage::BuildingIndices => 0.08,
+            GenerationStage::BuildingNormals => 0.04,
+            GenerationStage::Uploading => 0.03,
+        }
+    }
+}
+
+/// Progress of terrain generation, updated by the generation task and read by the loading screen.
+pub struct GenerationProgress {
+    started: Instant,
+    stage: AtomicU8,
+    completed: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl Default for GenerationProgress {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            stage: AtomicU8::new(0),
+            completed: AtomicUsize::new(0),
+            total: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl GenerationProgress {
+    pub fn start_stage(&self, stage: GenerationStage, total: usize) {
+        self.completed.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        self.stage.store(stage as u8, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, amount: usize) {
+        self.completed.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn stage(&self) -> GenerationStage {
+        GenerationStage::ALL[self.stage.load(Ordering::Relaxed) as usize]
+    }
+
+    /// Returns the completed and total work units of the current stage.
+    pub fn stage_progress(&self) -> (usize, usize) {
+        let total = self.total.load(Ordering::Relaxed);
+        let completed = self.completed.load(Ordering::Relaxed).min(total);
+        (completed, total)
+    }
+
+    /// Overall progress in `[0, 1]`, weighting each stage by its typical duration.
+    pub fn fraction(&self) -> f32 {
+        let stage = self.stage();
+        let (completed, total) = self.stage_progress();
+        let stage_fraction = if total > 0 {
+            completed as f32 / total as f32
+        } else {
+            0.0
+        };
+
+        let finished: f32 = GenerationStage::ALL
+            .iter()
+            .take_while(|s| **s != stage)
+            .map(|s| s.weight())
+            .sum();
+
+        (finished + stage.weight() * stage_fraction).clamp(0.0, 1.0)
+    }
+
+    /// Estimated time remaining, extrapolated from the progress made so far.
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction();
+        if fraction < 0.01 {
+            return None;
+        }
+
+        let elapsed = self.started.elapsed().as_secs_f32();
+        Some(Duration::from_secs_f32(
+            elapsed * (1.0 - fraction) / fraction,
+        ))
+    }
+}
+
+#[derive(Resource, Clone, Default)]
+pub struct TerrainGenerationProgress(pub Arc<GenerationProgress>);
@@ -0,0 +1,616 @@
+use crate::origin::WorldOrigin;
+use crate::terrain::{sample_f64, splat_weights_f64, TerrainSettings};
+use bevy::ecs::system::SystemParam;
+use bevy::math::{DVec2, DVec3};
+use bevy::prelude::*;
+
+/// Number of grid quads along each side of a leaf block of the min/max quadtree.
+const BLOCK_QUADS: usize = 8;
+
+/// Heights of the full-resolution terrain grid, kept after generation for gameplay queries.
+#[derive(Resource)]
+pub struct TerrainHeightfield {
+    /// World-space x and z of the first vertex.
+    origin: DVec2,
+    resolution: usize,
+    /// Row-major `(resolution + 1)²` vertex heights, with rows along x and columns along z.
+    heights: Vec<f32>,
+    /// Min/max quadtree over the heights, from the leaf blocks up to the single root node.
+    levels: Vec<MinMaxLevel>,
+}
+
+/// Height bounds of the nodes of one quadtree level, in row-major order.
+struct MinMaxLevel {
+    nodes_per_side: usize,
+    bounds: Vec<(f32, f32)>,
+}
+
+/// Point where a ray meets the terrain.
+#[derive(Debug, Clone, Copy)]
+pub struct TerrainHit {
+    pub position: Vec3,
+    /// Distance from the ray origin to [`TerrainHit::position`].
+    pub distance: f32,
+    /// Normal of the hit triangle.
+    pub normal: Vec3,
+}
+
+impl TerrainHeightfield {
+    pub fn new(origin: DVec2, resolution: usize, heights: Vec<f32>) -> Self {
+        assert_eq!(heights.len(), (resolution + 1) * (resolution + 1));
+        let mut heightfield = Self {
+            origin,
+            resolution,
+            heights,
+            levels: Vec::new(),
+        };
+        heightfield.build_quadtree();
+        heightfield
+    }
+
+    /// World-space x and z of the first vertex.
+    pub fn origin(&self) -> DVec2 {
+        self.origin
+    }
+
+    pub fn resolution(&self) -> usize {
+        self.resolution
+    }
+
+    /// Row-major `(resolution + 1)²` vertex heights, with rows along x and columns along z.
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    fn build_quadtree(&mut self) {
+        let blocks_per_side = self.resolution.div_ceil(BLOCK_QUADS);
+        let mut bounds = Vec::with_capacity(blocks_per_side * blocks_per_side);
+        for block_row in 0..blocks_per_side {
+            let rows =
+                block_row * BLOCK_QUADS..=((block_row + 1) * BLOCK_QUADS).min(self.resolution);
+            for block_col in 0..blocks_per_side {
+                let cols =
+                    block_col * BLOCK_QUADS..=((block_col + 1) * BLOCK_QUADS).min(self.resolution);
+                let mut node = (f32::MAX, f32::MIN);
+                for row in rows.clone() {
+                    for col in cols.clone() {
+                        let height = self.vertex(row, col);
+                        node = (node.0.min(height), node.1.max(height));
+                    }
+                }
+                bounds.push(node);
+            }
+        }
+        self.levels.push(MinMaxLevel {
+            nodes_per_side: blocks_per_side,
+            bounds,
+        });
+
+        while self.levels.last().unwrap().nodes_per_side > 1 {
+            let children = self.levels.last().unwrap();
+            let nodes_per_side = children.nodes_per_side.div_ceil(2);
+            let mut bounds = vec![(f32::MAX, f32::MIN); nodes_per_side * nodes_per_side];
+            for row in 0..children.nodes_per_side {
+                for col in 0..children.nodes_per_side {
+                    let child = children.bounds[row * children.nodes_per_side + col];
+                    let node = &mut bounds[(row / 2) * nodes_per_side + col / 2];
+                    *node = (node.0.min(child.0), node.1.max(child.1));
+                }
+            }
+            self.levels.push(MinMaxLevel {
+                nodes_per_side,
+                bounds,
+            });
+        }
+    }
+
+    fn vertex(&self, row: usize, col: usize) -> f32 {
+        self.heights[row * (self.resolution + 1) + col]
+    }
+
+    /// Finds the quad containing a world-space point, returning its first row and column and the
+    /// position of the point within it, or `None` outside the terrain.
+    fn locate(&self, point: DVec2) -> Option<(usize, usize, f32, f32)> {
+        let local = point - self.origin;
+        let size = self.resolution as f64;
+        if !(0.0..=size).contains(&local.x) || !(0.0..=size).contains(&local.y) {
+            return None;
+        }
+
+        // Points on the far edges belong to the last quad.
+        let row = (local.x as usize).min(self.resolution - 1);
+        let col = (local.y as usize).min(self.resolution - 1);
+        let u = (local.x - row as f64) as f32;
+        let v = (local.y - col as f64) as f32;
+        Some((row, col, u, v))
+    }
+
+    /// Returns the height of the rendered full-resolution surface at a world-space point, or
+    /// `None` outside the terrain.
+    ///
+    /// The height is interpolated over the same two triangles per quad as the chunk meshes, so it
+    /// matches the rendered surface exactly rather than a bilinear approximation of it.
+    pub fn height_at(&self, point: DVec2) -> Option<f32> {
+        let (row, col, u, v) = self.locate(point)?;
+
+        let top_left = self.vertex(row, col);
+        let top_right = self.vertex(row, col + 1);
+        let bottom_left = self.vertex(row + 1, col);
+        let bottom_right = self.vertex(row + 1, col + 1);
+
+        // Each quad is split along the diagonal from top right to bottom left.
+        let height = if u + v <= 1.0 {
+            top_left + u * (bottom_left - top_left) + v * (top_right - top_left)
+        } else {
+            bottom_right
+                + (1.0 - u) * (top_right - bottom_right)
+                + (1.0 - v) * (bottom_left - bottom_right)
+        };
+
+        Some(height)
+    }
+
+    /// Returns the unit normal of the triangle [`TerrainHeightfield::height_at`] interpolates over
+    /// at a world-space point, or `None` outside the terrain.
+    pub fn normal_at(&self, point: DVec2) -> Option<Vec3> {
+        let (row, col, u, v) = self.locate(point)?;
+
+        let top_left = self.vertex(row, col);
+        let top_right = self.vertex(row, col + 1);
+        let bottom_left = self.vertex(row + 1, col);
+        let bottom_right = self.vertex(row + 1, col + 1);
+
+        // Height gradient of the triangle along x and z.
+        let (dx, dz) = if u + v <= 1.0 {
+            (bottom_left - top_left, top_right - top_left)
+        } else {
+            (bottom_right - top_right, bottom_right - bottom_left)
+        };
+
+        Some(Vec3::new(-dx, 1.0, -dz).normalize())
+    }
+
+    /// Returns the first intersection of a world-space ray with the rendered full-resolution
+    /// surface within `max_distance`, as the distance along the ray and the triangle normal.
+    ///
+    /// The ray descends the min/max quadtree nearest node first, skipping every node whose height
+    /// range it passes over or under, and only tests the triangles of the leaf blocks it reaches.
+    pub fn raycast(
+        &self,
+        origin: DVec3,
+        direction: Dir3,
+        max_distance: f32,
+    ) -> Option<(f32, Vec3)> {
+        // Work relative to the first vertex, where f32 is precise enough for the whole grid.
+        let local_origin = (origin - DVec3::new(self.origin.x, 0.0, self.origin.y)).as_vec3();
+        let ray = LocalRay {
+            origin: local_origin,
+            direction: *direction,
+            inverse_direction: direction.recip(),
+        };
+
+        let root = self.levels.len() - 1;
+        self.raycast_node(&ray, root, 0, 0, max_distance)
+    }
+
+    fn raycast_node(
+        &self,
+        ray: &LocalRay,
+        level: usize,
+        row: usize,
+        col: usize,
+        max_distance: f32,
+    ) -> Option<(f32, Vec3)> {
+        if level == 0 {
+            return self.raycast_block(ray, row, col, max_distance);
+        }
+
+        let children = &self.levels[level - 1];
+        let mut entries: Vec<(f32, usize, usize)> = Vec::with_capacity(4);
+        for child_row in 2 * row..(2 * row + 2).min(children.nodes_per_side) {
+            for child_col in 2 * col..(2 * col + 2).min(children.nodes_per_side) {
+                if let Some(entry) =
+                    self.node_entry(ray, level - 1, child_row, child_col, max_distance)
+                {
+                    entries.push((entry, child_row, child_col));
+                }
+            }
+        }
+
+        // Children do not overlap horizontally, so the first one with a hit holds the nearest hit.
+        entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+        entries.into_iter().find_map(|(_, child_row, child_col)| {
+            self.raycast_node(ray, level - 1, child_row, child_col, max_distance)
+        })
+    }
+
+    /// Returns the distance at which the ray enters the bounding box of a node, if it does so
+    /// before `max_distance`.
+    fn node_entry(
+        &self,
+        ray: &LocalRay,
+        level: usize,
+        row: usize,
+        col: usize,
+        max_distance: f32,
+    ) -> Option<f32> {
+        let span = BLOCK_QUADS << level;
+        let (min_height, max_height) =
+            self.levels[level].bounds[row * self.levels[level].nodes_per_side + col];
+
+        let min = Vec3::new((row * span) as f32, min_height, (col * span) as f32);
+        let max = Vec3::new(
+            ((row + 1) * span).min(self.resolution) as f32,
+            max_height,
+            ((col + 1) * span).min(self.resolution) as f32,
+        );
+
+        let t0 = (min - ray.origin) * ray.inverse_direction;
+        let t1 = (max - ray.origin) * ray.inverse_direction;
+        // A ray running within a face of the box gives 0 * inf = NaN on that axis. It touches the
+        // box, so the axis must not limit the entry or exit.
+        let on_face = t0.is_nan_mask() | t1.is_nan_mask();
+        let entry = Vec3::select(on_face, Vec3::NEG_INFINITY, t0.min(t1))
+            .max_element()
+            .max(0.0);
+        let exit = Vec3::select(on_face, Vec3::INFINITY, t0.max(t1))
+            .min_element()
+            .min(max_distance);
+
+        (entry <= exit).then_some(entry)
+    }
+
+    fn raycast_block(
+        &self,
+        ray: &LocalRay,
+        block_row: usize,
+        block_col: usize,
+        max_distance: f32,
+    ) -> Option<(f32, Vec3)> {
+        let vertex =
+            |row: usize, col: usize| Vec3::new(row as f32, self.vertex(row, col), col as f32);
+
+        let mut nearest: Option<(f32, Vec3)> = None;
+        for row in block_row * BLOCK_QUADS..((block_row + 1) * BLOCK_QUADS).min(self.resolution) {
+            for col in block_col * BLOCK_QUADS..((block_col + 1) * BLOCK_QUADS).min(self.resolution)
+            {
+                let top_left = vertex(row, col);
+                let top_right = vertex(row, col + 1);
+                let bottom_left = vertex(row + 1, col);
+                let bottom_right = vertex(row + 1, col + 1);
+
+                for triangle in [
+                    [top_left, top_right, bottom_left],
+                    [top_right, bottom_right, bottom_left],
+                ] {
+                    let limit = nearest.map_or(max_distance, |(distance, _)| distance);
+                    if let Some(hit) = ray.intersect_triangle(triangle, limit) {
+                        nearest = Some(hit);
+                    }
+                }
+            }
+        }
+
+        nearest
+    }
+}
+
+struct LocalRay {
+    origin: Vec3,
+    direction: Vec3,
+    inverse_direction: Vec3,
+}
+
+impl LocalRay {
+    /// Möller–Trumbore intersection, returning the distance and the upward-facing normal.
+    fn intersect_triangle(&self, [a, b, c]: [Vec3; 3], max_distance: f32) -> Option<(f32, Vec3)> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(edge2);
+        let determinant = edge1.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+
+        let inverse_determinant = 1.0 / determinant;
+        let s = self.origin - a;
+        let u = s.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(edge1);
+        let v = self.direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(q) * inverse_determinant;
+        if !(0.0..=max_distance).contains(&distance) {
+            return None;
+        }
+
+        let normal = edge1.cross(edge2).normalize();
+        let normal = if normal.y < 0.0 { -normal } else { normal };
+        Some((distance, normal))
+    }
+}
+
+/// Ground cover at a terrain point, following the same rules as the rendered splat colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Biome {
+    Rock,
+    Snow,
+    Forest,
+}
+
+impl Biome {
+    /// Picks the cover that dominates the rendered color for the given snow and tree blend weights.
+    fn from_splat([snow_blend, tree_blend]: [f32; 2]) -> Self {
+        // Trees are blended over snow, so they win when both are present.
+        if tree_blend >= 0.5 {
+            Biome::Forest
+        } else if snow_blend >= 0.5 {
+            Biome::Snow
+        } else {
+            Biome::Rock
+        }
+    }
+}
+
+/// Answers ground queries at render-space positions, such as those of a [`Transform`].
+///
+/// Every query returns `None` while the terrain is still loading or when the point lies outside
+/// the terrain. Heights, normals and slopes are those of the flat triangles of the full-resolution
+/// grid, so they agree with [`TerrainQuery::raycast`]. Biomes are evaluated from the terrain
+/// function in double precision, as they are for the mesh vertices.
+#[derive(SystemParam)]
+pub struct TerrainQuery<'w> {
+    heightfield: Option<Res<'w, TerrainHeightfield>>,
+    settings: Res<'w, TerrainSettings>,
+    world_origin: Res<'w, WorldOrigin>,
+}
+
+impl TerrainQuery<'_> {
+    fn world_point(&self, x: f32, z: f32) -> DVec3 {
+        self.world_origin.to_world(Vec3::new(x, 0.0, z))
+    }
+
+    /// Returns the render-space height of the ground below `(x, z)`.
+    pub fn height_at(&self, x: f32, z: f32) -> Option<f32> {
+        let point = self.world_point(x, z);
+        let height = self.heightfield.as_ref()?.height_at(point.xz())?;
+        Some((height as f64 - self.world_origin.0.y) as f32)
+    }
+
+    /// Returns the unit normal of the ground triangle below `(x, z)`.
+    ///
+    /// This is the faceted normal of the surface [`TerrainQuery::height_at`] follows, not the
+    /// smooth vertex normal the terrain is shaded with.
+    pub fn normal_at(&self, x: f32, z: f32) -> Option<Vec3> {
+        let point = self.world_point(x, z);
+        self.heightfield.as_ref()?.normal_at(point.xz())
+    }
+
+    /// Returns the angle, in radians, between the ground at `(x, z)` and the horizontal.
+    pub fn slope_at(&self, x: f32, z: f32) -> Option<f32> {
+        let normal = self.normal_at(x, z)?;
+        Some(normal.y.clamp(-1.0, 1.0).acos())
+    }
+
+    /// Casts a render-space ray against the terrain, returning the nearest hit within
+    /// `max_distance`.
+    pub fn raycast(&self, ray: Ray3d, max_distance: f32) -> Option<TerrainHit> {
+        let origin = self.world_origin.to_world(ray.origin);
+        let (distance, normal) =
+            self.heightfield
+                .as_ref()?
+                .raycast(origin, ray.direction, max_distance)?;
+
+        Some(TerrainHit {
+            position: ray.get_point(distance),
+            distance,
+            normal,
+        })
+    }
+
+    pub fn biome_at(&self, x: f32, z: f32) -> Option<Biome> {
+        let point = self.world_point(x, z);
+        let height = self.heightfield.as_ref()?.height_at(point.xz())?;
+        let (_, normal) = sample_f64(&self.settings, point.x, point.z);
+
+        let splat = splat_weights_f64(self.settings.seed, point.x, point.z, height, normal);
+        Some(Biome::from_splat(splat))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Not a multiple of [`BLOCK_QUADS`], so the last row and column of blocks are partial.
+    const RESOLUTION: usize = 50;
+    const ORIGIN: DVec2 = DVec2::new(-25.0, 40.0);
+
+    fn hilly_heightfield() -> TerrainHeightfield {
+        let stride = RESOLUTION + 1;
+        let heights = (0..stride * stride)
+            .map(|index| {
+                let (row, col) = ((index / stride) as f32, (index % stride) as f32);
+                (row * 0.37).sin() * 6.0 + (col * 0.23).cos() * 4.0 + (row * col * 0.01).sin()
+            })
+            .collect();
+        TerrainHeightfield::new(ORIGIN, RESOLUTION, heights)
+    }
+
+    /// Nearest hit found by testing every triangle of the grid, without the quadtree.
+    fn brute_force_raycast(
+        heightfield: &TerrainHeightfield,
+        origin: DVec3,
+        direction: Dir3,
+        max_distance: f32,
+    ) -> Option<(f32, Vec3)> {
+        let ray = LocalRay {
+            origin: (origin - DVec3::new(ORIGIN.x, 0.0, ORIGIN.y)).as_vec3(),
+            direction: *direction,
+            inverse_direction: direction.recip(),
+        };
+        let blocks_per_side = RESOLUTION.div_ceil(BLOCK_QUADS);
+        (0..blocks_per_side * blocks_per_side)
+            .filter_map(|block| {
+                let (row, col) = (block / blocks_per_side, block % blocks_per_side);
+                heightfield.raycast_block(&ray, row, col, max_distance)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    /// Checks a ray against the brute-force result and checks that the hit lies on the surface
+    /// described by [`TerrainHeightfield::height_at`].
+    fn check_ray(heightfield: &TerrainHeightfield, origin: DVec3, direction: Vec3) {
+        let direction = Dir3::new(direction).unwrap();
+        let hit = heightfield.raycast(origin, direction, 1000.0);
+        let expected = brute_force_raycast(heightfield, origin, direction, 1000.0);
+        let (distance, _) = match (hit, expected) {
+            (Some(hit), Some(expected)) => {
+                assert!(
+                    (hit.0 - expected.0).abs() < 1e-3,
+                    "ray from {origin} along {direction:?} hit at {} instead of {}",
+                    hit.0,
+                    expected.0
+                );
+                hit
+            }
+            (None, None) => return,
+            _ => panic!("ray from {origin} along {direction:?}: {hit:?} != {expected:?}"),
+        };
+
+        let point = origin + direction.as_dvec3() * distance as f64;
+        let height = heightfield.height_at(point.xz()).unwrap();
+        assert!(
+            (point.y as f32 - height).abs() < 1e-3,
+            "ray from {origin} along {direction:?} hit at height {} above ground at {height}",
+            point.y
+        );
+    }
+
+    #[test]
+    fn vertical_rays_hit_the_ground_below() {
+        let heightfield = hilly_heightfield();
+        for step in 0..=4 * RESOLUTION {
+            let x = ORIGIN.x + step as f64 * 0.25;
+            let z = ORIGIN.y + (step * 7 % (4 * RESOLUTION + 1)) as f64 * 0.25;
+            let origin = DVec3::new(x, 100.0, z);
+
+            let (distance, normal) = heightfield.raycast(origin, Dir3::NEG_Y, 1000.0).unwrap();
+            let height = heightfield.height_at(DVec2::new(x, z)).unwrap();
+            assert!((100.0 - distance - height).abs() < 1e-3, "at ({x}, {z})");
+            assert!(normal.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn normals_match_the_triangles_rays_hit() {
+        let heightfield = hilly_heightfield();
+        for row in 0..RESOLUTION {
+            for col in 0..RESOLUTION {
+                // One point inside each triangle of the quad, away from the shared diagonal.
+                for (u, v) in [(0.3, 0.2), (0.8, 0.7)] {
+                    let x = ORIGIN.x + row as f64 + u;
+                    let z = ORIGIN.y + col as f64 + v;
+
+                    let (_, hit_normal) = heightfield
+                        .raycast(DVec3::new(x, 100.0, z), Dir3::NEG_Y, 1000.0)
+                        .unwrap();
+                    let normal = heightfield.normal_at(DVec2::new(x, z)).unwrap();
+                    assert!(normal.abs_diff_eq(hit_normal, 1e-5), "at ({x}, {z})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn slanted_rays_match_every_triangle() {
+        let heightfield = hilly_heightfield();
+        for index in 0..200 {
+            let angle = index as f32 * 0.61;
+            let origin = DVec3::new(
+                ORIGIN.x - 10.0 + (index * 13 % 70) as f64 * 0.9,
+                20.0 + (index % 5) as f64 * 10.0,
+                ORIGIN.y - 10.0 + (index * 29 % 70) as f64 * 0.9,
+            );
+            let descent = 0.2 + (index % 7) as f32 * 0.3;
+            check_ray(
+                &heightfield,
+                origin,
+                Vec3::new(angle.cos(), -descent, angle.sin()),
+            );
+        }
+    }
+
+    #[test]
+    fn grazing_rays_match_every_triangle() {
+        let heightfield = hilly_heightfield();
+        for index in 0..200 {
+            let angle = index as f32 * 0.37;
+            let start = DVec2::new(
+                ORIGIN.x + (index * 17 % RESOLUTION) as f64 + 0.5,
+                ORIGIN.y + (index * 31 % RESOLUTION) as f64 + 0.5,
+            );
+            // Start just above the ground and run almost parallel to it.
+            let height = heightfield.height_at(start).unwrap() as f64 + 0.05;
+            let descent = (index % 4) as f32 * 0.002 - 0.001;
+            check_ray(
+                &heightfield,
+                DVec3::new(start.x, height, start.y),
+                Vec3::new(angle.cos(), descent, angle.sin()),
+            );
+        }
+    }
+
+    #[test]
+    fn rays_on_block_boundaries_match_every_triangle() {
+        let heightfield = hilly_heightfield();
+        let size = RESOLUTION as f64;
+        for block in 0..=RESOLUTION / BLOCK_QUADS {
+            let line = (block * BLOCK_QUADS) as f64;
+            for offset in [0.0, 3.5, size] {
+                // Vertical rays through block corners and edges.
+                check_ray(
+                    &heightfield,
+                    DVec3::new(ORIGIN.x + line, 50.0, ORIGIN.y + offset),
+                    Vec3::NEG_Y,
+                );
+                // Slanted rays running along a block boundary in x and in z.
+                check_ray(
+                    &heightfield,
+                    DVec3::new(ORIGIN.x + line, 30.0, ORIGIN.y + offset),
+                    Vec3::new(0.0, -0.4, 1.0),
+                );
+                check_ray(
+                    &heightfield,
+                    DVec3::new(ORIGIN.x + offset, 30.0, ORIGIN.y + line),
+                    Vec3::new(-1.0, -0.4, 0.0),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rays_that_miss_return_none() {
+        let heightfield = hilly_heightfield();
+        let center = DVec3::new(ORIGIN.x + 25.0, 0.0, ORIGIN.y + 25.0);
+
+        // Pointing up from above the ground.
+        let above = center + DVec3::Y * 50.0;
+        assert!(heightfield.raycast(above, Dir3::Y, 1000.0).is_none());
+        // Level above the highest point.
+        assert!(heightfield.raycast(above, Dir3::X, 1000.0).is_none());
+        // Pointing down next to the terrain.
+        let outside = DVec3::new(ORIGIN.x - 1.0, 50.0, ORIGIN.y + 25.0);
+        assert!(heightfield.raycast(outside, Dir3::NEG_Y, 1000.0).is_none());
+        // Pointing away from the terrain.
+        assert!(heightfield.raycast(outside, Dir3::NEG_X, 1000.0).is_none());
+        // Stopping short of the ground.
+        assert!(heightfield.raycast(above, Dir3::NEG_Y, 30.0).is_none());
+    }
+}
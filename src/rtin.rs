@@ -0,0 +1,398 @@
+//! Right-triangulated irregular network (RTIN) simplification of a square height grid.
+//!
+//! The grid is recursively split into right triangles along their hypotenuse, and a triangle is
+//! only split further if the height at the midpoint of its hypotenuse, or at any grid point it
+//! covers, deviates from the linear interpolation by more than the requested error. See
+//! "Right-Triangulated Irregular Networks" by Evans, Kirkpatrick and Townsend (1997).
+
+/// Precomputed approximation errors for a `(2^k + 1)²` height grid.
+pub struct Rtin {
+    grid_size: usize,
+    heights: Vec<f32>,
+    errors: Vec<f32>,
+}
+
+impl Rtin {
+    /// Computes the error hierarchy for a row-major grid of heights.
+    ///
+    /// Panics if the grid is not `(2^k + 1)` vertices on each side.
+    pub fn new(heights: &[f32], grid_size: usize) -> Self {
+        let tile_size = grid_size - 1;
+        assert!(
+            tile_size.is_power_of_two(),
+            "RTIN grid size must be 2^k + 1, got {grid_size}"
+        );
+        assert_eq!(heights.len(), grid_size * grid_size);
+
+        let triangle_count = tile_size * tile_size * 2 - 2;
+        let parent_count = triangle_count - tile_size * tile_size;
+
+        let mut errors = vec![0.0_f32; grid_size * grid_size];
+
+        // Visit the triangles of the full-resolution hierarchy bottom-up, so that each parent
+        // accumulates the errors of its children before its own error is consulted.
+        for i in (0..triangle_count).rev() {
+            let [ax, ay, bx, by] = triangle_coords(i, tile_size);
+            let (mx, my) = ((ax + bx) / 2, (ay + by) / 2);
+            let (cx, cy) = (mx + my - ay, my + ax - mx);
+
+            let interpolated = (heights[ay * grid_size + ax] + heights[by * grid_size + bx]) / 2.0;
+            let middle = my * grid_size + mx;
+            let middle_error = (interpolated - heights[middle]).abs();
+
+            errors[middle] = errors[middle].max(middle_error);
+
+            if i < parent_count {
+                let left_child = ((ay + cy) / 2) * grid_size + (ax + cx) / 2;
+                let right_child = ((by + cy) / 2) * grid_size + (bx + cx) / 2;
+                errors[middle] = errors[middle]
+                    .max(errors[left_child])
+                    .max(errors[right_child]);
+            }
+        }
+
+        Self {
+            grid_size,
+            heights: heights.to_vec(),
+            errors,
+        }
+    }
+
+    /// Triangulates the grid so that no grid point deviates from the triangle covering it by more
+    /// than `max_error`.
+    ///
+    /// Returns the grid indices of the vertices that are used, followed by triangle indices into
+    /// that vertex list. Triangles use the same winding as the full-resolution grid mesh.
+    pub fn triangulate(&self, max_error: f32) -> (Vec<u32>, Vec<u32>) {
+        let max = self.grid_size - 1;
+        self.build(max_error, max, max, false)
+    }
+
+    /// [`Rtin::triangulate`] for a terrain chunk covering the first `width` columns and `height`
+    /// rows of grid quads, which is less than the whole grid at the far edges of the terrain.
+    ///
+    /// Every grid vertex on the border of the chunk is kept, so the chunk meets its neighbours,
+    /// simplified or not, without cracks.
+    pub fn triangulate_chunk(
+        &self,
+        max_error: f32,
+        width: usize,
+        height: usize,
+    ) -> (Vec<u32>, Vec<u32>) {
+        self.build(max_error, width, height, true)
+    }
+
+    fn build(
+        &self,
+        max_error: f32,
+        width: usize,
+        height: usize,
+        keep_border: bool,
+    ) -> (Vec<u32>, Vec<u32>) {
+        let max = self.grid_size - 1;
+        assert!(width <= max && height <= max);
+
+        let mut builder = MeshBuilder {
+            rtin: self,
+            max_error,
+            width,
+            height,
+            keep_border,
+            remap: vec![u32::MAX; self.grid_size * self.grid_size],
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        };
+
+        builder.process(0, 0, max, max, max, 0);
+        builder.process(max, max, 0, 0, 0, max);
+
+        (builder.vertices, builder.indices)
+    }
+}
+
+/// Returns the two hypotenuse corners `[ax, ay, bx, by]` of triangle `i` in the implicit binary
+/// tree of right triangles covering a `tile_size` square.
+fn triangle_coords(i: usize, tile_size: usize) -> [usize; 4] {
+    let mut id = i + 2;
+    let (mut ax, mut ay, mut bx, mut by, mut cx, mut cy) = (0, 0, 0, 0, 0, 0);
+
+    if id & 1 == 1 {
+        // bottom-left triangle
+        bx = tile_size;
+        by = tile_size;
+        cx = tile_size;
+    } else {
+        // top-right triangle
+        ax = tile_size;
+        ay = tile_size;
+        cy = tile_size;
+    }
+
+    loop {
+        id >>= 1;
+        if id <= 1 {
+            break;
+        }
+
+        let (mx, my) = ((ax + bx) / 2, (ay + by) / 2);
+
+        if id & 1 == 1 {
+            // left half
+            bx = ax;
+            by = ay;
+            ax = cx;
+            ay = cy;
+        } else {
+            // right half
+            ax = bx;
+            ay = by;
+            bx = cx;
+            by = cy;
+        }
+
+        cx = mx;
+        cy = my;
+    }
+
+    [ax, ay, bx, by]
+}
+
+struct MeshBuilder<'a> {
+    rtin: &'a Rtin,
+    max_error: f32,
+    /// Region of the grid that is triangulated, in quads.
+    width: usize,
+    height: usize,
+    keep_border: bool,
+    remap: Vec<u32>,
+    vertices: Vec<u32>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder<'_> {
+    fn process(&mut self, ax: usize, ay: usize, bx: usize, by: usize, cx: usize, cy: usize) {
+        let grid_size = self.rtin.grid_size;
+        let (mx, my) = ((ax + bx) / 2, (ay + by) / 2);
+
+        let (min_x, max_x) = (ax.min(bx).min(cx), ax.max(bx).max(cx));
+        let (min_y, max_y) = (ay.min(by).min(cy), ay.max(by).max(cy));
+        if min_x >= self.width || min_y >= self.height {
+            return;
+        }
+        let crosses_edge = max_x > self.width || max_y > self.height;
+        let corners = [(ax, ay), (bx, by), (cx, cy)];
+
+        let splittable = ax.abs_diff(cx) + ay.abs_diff(cy) > 1;
+        if splittable
+            && (crosses_edge
+                || self.skips_border_vertices(corners)
+                || self.rtin.errors[my * grid_size + mx] > self.max_error
+                || self.deviation(corners) > self.max_error)
+        {
+            self.process(cx, cy, ax, ay, mx, my);
+            self.process(bx, by, cx, cy, mx, my);
+            return;
+        }
+
+        let a = self.vertex(ay * grid_size + ax);
+        let b = self.vertex(by * grid_size + bx);
+        let c = self.vertex(cy * grid_size + cx);
+
+        // Match the winding of the full-resolution grid, where (row, col) -> (row, col + 1) ->
+        // (row + 1, col) is a front face.
+        let cross = (bx as isize - ax as isize) * (cy as isize - ay as isize)
+            - (by as isize - ay as isize) * (cx as isize - ax as isize);
+
+        if cross > 0 {
+            self.indices.extend([a, b, c]);
+        } else {
+            self.indices.extend([a, c, b]);
+        }
+    }
+
+    /// Returns true if an edge of the triangle runs along the border of the region past grid
+    /// vertices that a neighbouring chunk may use.
+    fn skips_border_vertices(&self, corners: [(usize, usize); 3]) -> bool {
+        if !self.keep_border {
+            return false;
+        }
+
+        (0..3).any(|i| {
+            let ((x0, y0), (x1, y1)) = (corners[i], corners[(i + 1) % 3]);
+            let along_x_border = x0 == x1 && (x0 == 0 || x0 == self.width);
+            let along_y_border = y0 == y1 && (y0 == 0 || y0 == self.height);
+            (along_x_border && y0.abs_diff(y1) > 1) || (along_y_border && x0.abs_diff(x1) > 1)
+        })
+    }
+
+    /// Largest vertical distance between a grid point covered by the triangle and the plane
+    /// through its corners.
+    ///
+    /// The error hierarchy only bounds the midpoints relative to their parent triangles, which
+    /// leaves points inside a large triangle unchecked against the triangle itself.
+    fn deviation(&self, corners: [(usize, usize); 3]) -> f32 {
+        let grid_size = self.rtin.grid_size;
+        let heights = &self.rtin.heights;
+        let [a, b, c] = corners.map(|(x, y)| (x as isize, y as isize));
+        let height = |(x, y): (isize, isize)| heights[y as usize * grid_size + x as usize];
+
+        // Twice the signed area of the triangle (p, q, r).
+        let edge = |p: (isize, isize), q: (isize, isize), r: (isize, isize)| {
+            (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0)
+        };
+        let area = edge(a, b, c);
+        let (height_a, height_b, height_c) = (height(a), height(b), height(c));
+
+        let mut deviation = 0.0_f32;
+        for y in a.1.min(b.1).min(c.1)..=a.1.max(b.1).max(c.1) {
+            for x in a.0.min(b.0).min(c.0)..=a.0.max(b.0).max(c.0) {
+                let point = (x, y);
+                let weights = [edge(b, c, point), edge(c, a, point), edge(a, b, point)];
+                if weights.iter().any(|weight| weight * area.signum() < 0) {
+                    continue;
+                }
+
+                let interpolated = height_a
+                    + (height_b - height_a) * (weights[1] as f32 / area as f32)
+                    + (height_c - height_a) * (weights[2] as f32 / area as f32);
+                deviation = deviation.max((interpolated - height(point)).abs());
+            }
+        }
+
+        deviation
+    }
+
+    fn vertex(&mut self, grid_index: usize) -> u32 {
+        if self.remap[grid_index] == u32::MAX {
+            self.remap[grid_index] = self.vertices.len() as u32;
+            self.vertices.push(grid_index as u32);
+        }
+
+        self.remap[grid_index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID_SIZE: usize = 65;
+
+    /// Rolling hills with a little hash noise on top, so that every level of the hierarchy has
+    /// something to simplify.
+    fn hilly_heights() -> Vec<f32> {
+        (0..GRID_SIZE * GRID_SIZE)
+            .map(|index| {
+                let (x, y) = ((index % GRID_SIZE) as f32, (index / GRID_SIZE) as f32);
+                let noise = (index as u32).wrapping_mul(2654435761) >> 24;
+                (x * 0.21).sin() * 6.0 + (y * 0.13).cos() * 4.0 + noise as f32 / 255.0 * 0.3
+            })
+            .collect()
+    }
+
+    /// Checks that the triangles cover every grid point of the region and that none of those
+    /// points deviates from its triangle by more than `max_error`.
+    fn check_deviation(
+        heights: &[f32],
+        (vertices, indices): &(Vec<u32>, Vec<u32>),
+        max_error: f32,
+        width: usize,
+        height: usize,
+    ) {
+        let point = |vertex: u32| {
+            let index = vertices[vertex as usize] as usize;
+            ((index % GRID_SIZE) as f64, (index / GRID_SIZE) as f64, heights[index] as f64)
+        };
+
+        let mut covered = vec![false; GRID_SIZE * GRID_SIZE];
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [point(triangle[0]), point(triangle[1]), point(triangle[2])];
+            let area = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+            assert!(area != 0.0, "degenerate triangle {triangle:?}");
+
+            for y in 0..=height {
+                for x in 0..=width {
+                    let (px, py) = (x as f64, y as f64);
+                    let u = ((c.0 - b.0) * (py - b.1) - (c.1 - b.1) * (px - b.0)) / area;
+                    let v = ((a.0 - c.0) * (py - c.1) - (a.1 - c.1) * (px - c.0)) / area;
+                    let w = 1.0 - u - v;
+                    if u < 0.0 || v < 0.0 || w < 0.0 {
+                        continue;
+                    }
+
+                    let index = y * GRID_SIZE + x;
+                    let interpolated = u * a.2 + v * b.2 + w * c.2;
+                    let deviation = (interpolated - heights[index] as f64).abs();
+                    assert!(
+                        deviation <= max_error as f64 + 1e-4,
+                        "({x}, {y}) deviates by {deviation} with a max error of {max_error}"
+                    );
+                    covered[index] = true;
+                }
+            }
+        }
+
+        for y in 0..=height {
+            for x in 0..=width {
+                assert!(covered[y * GRID_SIZE + x], "({x}, {y}) is not covered");
+            }
+        }
+    }
+
+    #[test]
+    fn flat_grid_is_two_triangles() {
+        let rtin = Rtin::new(&vec![12.5; GRID_SIZE * GRID_SIZE], GRID_SIZE);
+        let (vertices, indices) = rtin.triangulate(0.0);
+
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices.len(), 6);
+    }
+
+    #[test]
+    fn zero_error_keeps_the_full_grid() {
+        let heights = hilly_heights();
+        let (vertices, indices) = Rtin::new(&heights, GRID_SIZE).triangulate(0.0);
+
+        let tile_size = GRID_SIZE - 1;
+        assert_eq!(vertices.len(), GRID_SIZE * GRID_SIZE);
+        assert_eq!(indices.len() / 3, tile_size * tile_size * 2);
+    }
+
+    #[test]
+    fn deviation_stays_within_max_error() {
+        let heights = hilly_heights();
+        let rtin = Rtin::new(&heights, GRID_SIZE);
+        let max = GRID_SIZE - 1;
+
+        for max_error in [0.0, 0.1, 0.5, 2.0, 10.0] {
+            let triangulation = rtin.triangulate(max_error);
+            check_deviation(&heights, &triangulation, max_error, max, max);
+
+            for (width, height) in [(max, max), (40, max), (max, 17), (5, 33)] {
+                let triangulation = rtin.triangulate_chunk(max_error, width, height);
+                check_deviation(&heights, &triangulation, max_error, width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_keep_every_border_vertex() {
+        let rtin = Rtin::new(&vec![0.0; GRID_SIZE * GRID_SIZE], GRID_SIZE);
+
+        for (width, height) in [(64, 64), (40, 64), (64, 17), (5, 33)] {
+            let (vertices, _) = rtin.triangulate_chunk(1.0, width, height);
+            let points: Vec<(usize, usize)> = vertices
+                .iter()
+                .map(|&index| (index as usize % GRID_SIZE, index as usize / GRID_SIZE))
+                .collect();
+
+            assert!(points.iter().all(|&(x, y)| x <= width && y <= height));
+            for x in 0..=width {
+                assert!(points.contains(&(x, 0)) && points.contains(&(x, height)));
+            }
+            for y in 0..=height {
+                assert!(points.contains(&(0, y)) && points.contains(&(width, y)));
+            }
+        }
+    }
+}
@@ -1,16 +1,138 @@
-use crate::TerrainGenerationTask;
+use crate::cache::ChunkCache;
+use crate::camera::MainCamera;
+use crate::culling::{self, HorizonOccluders, TerrainCullingStats};
+use crate::input::{ActionInput, InputAction};
+use crate::noise::{fbm, fbm_f64, fbm_lanes, smoothstep_bounds, Lanes, LANES};
+use crate::origin::{self, WorldOrigin};
+use crate::progress::{GenerationProgress, GenerationStage, TerrainGenerationProgress};
+use crate::query::TerrainHeightfield;
+use crate::rtin::Rtin;
 use bevy::asset::RenderAssetUsages;
+use bevy::camera::primitives::Aabb;
+use bevy::camera::visibility::VisibilitySystems;
+use bevy::math::{DVec2, DVec3};
 use bevy::pbr::wireframe::Wireframe;
 use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, TaskPool};
+use bevy::transform::TransformSystems;
 use bevy_mesh::Indices;
 use futures_lite::future;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 use wgpu_types::PrimitiveTopology;
 
 const TREE_COLOR: Color = Color::srgb(0.51, 0.51, 0.1);
 const ROCK_COLOR: Color = Color::srgb(0.894, 0.675, 0.608);
 
+/// Default number of grid quads along each side of the terrain.
+pub const TERRAIN_RESOLUTION: usize = 5000;
+
+/// Number of grid quads along each side of a chunk. Must be a power of two for [`Rtin`].
+pub const CHUNK_SIZE: usize = 256;
+
+/// Version of the chunk data produced by [`generate_chunk_data`], part of the chunk cache key.
+///
+/// Bump whenever a change to the sampling, noise or splat code changes the generated heights,
+/// normals or splat weights, so that cached chunks are regenerated. The
+/// `chunk_data_matches_generator_version` test fails until this is done.
+pub const GENERATOR_VERSION: u32 = 2;
+
+/// Distance from the world origin beyond which terrain is sampled in double precision. Terrains
+/// reaching past it are sampled in double precision throughout, see [`uses_double_precision`].
+const DOUBLE_PRECISION_DISTANCE: f64 = 16384.0;
+
+/// Maximum vertical deviation, in metres, of the simplified chunk meshes from the full grid.
+pub const SIMPLIFIED_MAX_ERROR: f32 = 0.5;
+
+/// Distance from the camera beyond which chunks switch to their simplified mesh.
+const SIMPLIFICATION_DISTANCE: f32 = 1500.0;
+
+/// Parameters that fully determine the generated terrain.
+///
+/// Fields missing from a settings file keep their default values.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerrainSettings {
+    pub seed: u32,
+    /// Number of grid quads along each side of the terrain. The last row and column of chunks are
+    /// cut short when this is not a multiple of [`CHUNK_SIZE`].
+    pub resolution: usize,
+    /// Height, in metres, of the highest possible peak.
+    pub amplitude: f32,
+    /// Horizontal size, in metres, of the largest noise features.
+    pub scale: f32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            seed: 3266489917,
+            resolution: TERRAIN_RESOLUTION,
+            amplitude: 300.0,
+            scale: 800.0,
+        }
+    }
+}
+
 #[derive(Component, Clone, Copy)]
-pub struct Tile {}
+pub struct Tile {
+    /// World-space position of the first vertex of the chunk.
+    pub origin: DVec3,
+    /// Triangles in the mesh currently being rendered for this chunk.
+    pub triangle_count: usize,
+    /// Triangles in the full-resolution grid mesh of this chunk.
+    pub full_triangle_count: usize,
+}
+
+/// Full-resolution and simplified meshes of a chunk.
+#[derive(Component)]
+pub struct TileLod {
+    pub full: Handle<Mesh>,
+    pub simplified: Handle<Mesh>,
+    pub simplified_triangle_count: usize,
+}
+
+/// Heights, normals and splat weights sampled on the `(CHUNK_SIZE + 1)²` vertex grid of a chunk.
+pub struct ChunkData {
+    pub heights: Vec<f32>,
+    pub normals: Vec<[f32; 3]>,
+    /// Snow and tree cover blend weights.
+    pub splat: Vec<[f32; 2]>,
+}
+
+/// Mesh and bounds of a single terrain chunk, with positions relative to the chunk origin.
+pub struct TerrainChunk {
+    pub origin: DVec3,
+    /// Number of grid quads along x and z, less than [`CHUNK_SIZE`] at the far edges of a terrain
+    /// whose resolution is not a multiple of it.
+    pub size: UVec2,
+    pub mesh: Mesh,
+    pub simplified_mesh: Mesh,
+    pub min_height: f32,
+    pub max_height: f32,
+    pub triangle_count: usize,
+    pub simplified_triangle_count: usize,
+}
+
+pub struct GeneratedTerrain {
+    pub chunks: Vec<TerrainChunk>,
+    pub occluders: HorizonOccluders,
+    pub heightfield: TerrainHeightfield,
+    /// Normals of the full-resolution grid, laid out like the heights of the heightfield.
+    pub normals: Vec<[f32; 3]>,
+    /// Snow and tree cover blend weights of the full-resolution grid.
+    pub splat: Vec<[f32; 2]>,
+}
+
+/// Generated chunks that still have to be spawned, a few per frame.
+#[derive(Resource)]
+pub struct PendingChunks {
+    chunks: Vec<TerrainChunk>,
+    material: Handle<StandardMaterial>,
+}
+
+/// Number of chunks whose meshes are handed to the renderer per frame while loading.
+const CHUNKS_UPLOADED_PER_FRAME: usize = 8;
 
 #[derive(Component)]
 pub struct NormalLines;
@@ -20,6 +142,9 @@ pub struct TerrainManager {
     pub loaded: bool,
     pub wireframe_mode: bool,
     pub show_normals: bool,
+    pub simplify_distant_chunks: bool,
+    /// Directory generated chunks are cached in, or `None` to always generate every chunk.
+    pub cache_dir: Option<PathBuf>,
 }
 
 impl Default for TerrainManager {
@@ -28,17 +153,127 @@ impl Default for TerrainManager {
             loaded: false,
             wireframe_mode: false,
             show_normals: false,
+            simplify_distant_chunks: true,
+            cache_dir: None,
+        }
+    }
+}
+
+/// Generates the terrain in the background when the app starts, spawns its chunks, and then keeps
+/// them simplified, culled and positioned relative to the [`MainCamera`].
+///
+/// Ground queries are answered by [`TerrainQuery`](crate::query::TerrainQuery) once
+/// [`TerrainManager::loaded`] is set.
+pub struct TerrainPlugin {
+    pub settings: TerrainSettings,
+    /// Directory generated chunks are cached in, or `None`, the default, to always generate every
+    /// chunk.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl TerrainPlugin {
+    pub fn new(settings: TerrainSettings) -> Self {
+        Self {
+            settings,
+            cache_dir: None,
         }
     }
+
+    /// Reads generated chunks from, and writes them to, a [`ChunkCache`] in `dir`.
+    pub fn with_cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+}
+
+impl Default for TerrainPlugin {
+    fn default() -> Self {
+        Self::new(TerrainSettings::default())
+    }
+}
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .insert_resource(TerrainManager {
+                cache_dir: self.cache_dir.clone(),
+                ..default()
+            })
+            .init_resource::<TerrainCullingStats>()
+            .init_resource::<WorldOrigin>()
+            .add_systems(
+                Update,
+                (
+                    start_terrain_generation,
+                    check_terrain_generation,
+                    upload_terrain_chunks,
+                )
+                    .run_if(terrain_loading),
+            )
+            .add_systems(
+                Update,
+                (origin::recenter_origin_system, update_chunk_lod_system).run_if(terrain_loaded),
+            )
+            .add_systems(
+                PostUpdate,
+                culling::terrain_culling_system
+                    .after(TransformSystems::Propagate)
+                    .before(VisibilitySystems::VisibilityPropagate)
+                    .run_if(terrain_loaded),
+            );
+    }
+}
+
+/// Run condition for systems that wait on the terrain, true once every chunk has been spawned.
+pub fn terrain_loaded(terrain_manager: Res<TerrainManager>) -> bool {
+    terrain_manager.loaded
+}
+
+fn terrain_loading(terrain_manager: Res<TerrainManager>) -> bool {
+    !terrain_manager.loaded
+}
+
+#[derive(Component)]
+struct TerrainGenerationTask(Task<(GeneratedTerrain, Mesh)>);
+
+fn start_terrain_generation(
+    mut commands: Commands,
+    progress: Option<Res<TerrainGenerationProgress>>,
+    settings: Res<TerrainSettings>,
+    terrain_manager: Res<TerrainManager>,
+) {
+    if progress.is_none() {
+        let thread_pool = AsyncComputeTaskPool::get();
+        let settings = settings.clone();
+        let cache_dir = terrain_manager.cache_dir.clone();
+
+        let progress = TerrainGenerationProgress::default();
+        commands.insert_resource(progress.clone());
+
+        let task = thread_pool.spawn(async move {
+            let cache = cache_dir.and_then(|dir| {
+                ChunkCache::open(dir, &settings)
+                    .inspect_err(|error| warn!("Terrain cache unavailable: {error}"))
+                    .ok()
+            });
+
+            let terrain = generate_terrain_mesh(&settings, cache.as_ref(), &progress.0);
+            let normal_lines =
+                build_normal_lines(Some(AsyncComputeTaskPool::get()), &terrain, &progress.0);
+            (terrain, normal_lines)
+        });
+
+        commands.spawn(TerrainGenerationTask(task));
+    }
 }
 
 pub fn toggle_wireframe_system(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
     mut terrain_manager: ResMut<TerrainManager>,
     mut commands: Commands,
     tile_query: Query<Entity, With<Tile>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::KeyM) {
+    if input.just_pressed(InputAction::ToggleWireframe) {
         terrain_manager.wireframe_mode = !terrain_manager.wireframe_mode;
 
         for entity in tile_query.iter() {
@@ -52,11 +287,11 @@ pub fn toggle_wireframe_system(
 }
 
 pub fn toggle_normals_system(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
     mut terrain_manager: ResMut<TerrainManager>,
     mut normal_lines_query: Query<&mut Visibility, With<NormalLines>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::KeyN) {
+    if input.just_pressed(InputAction::ToggleNormals) {
         terrain_manager.show_normals = !terrain_manager.show_normals;
 
         if let Ok(mut visibility) = normal_lines_query.single_mut() {
@@ -69,54 +304,502 @@ pub fn toggle_normals_system(
     }
 }
 
+pub fn toggle_simplification_system(
+    input: ActionInput,
+    mut terrain_manager: ResMut<TerrainManager>,
+) {
+    if input.just_pressed(InputAction::ToggleSimplification) {
+        terrain_manager.simplify_distant_chunks = !terrain_manager.simplify_distant_chunks;
+    }
+}
+
+/// Swaps each chunk between its full and simplified mesh based on its distance to the camera.
+pub fn update_chunk_lod_system(
+    terrain_manager: Res<TerrainManager>,
+    camera_query: Query<&GlobalTransform, With<MainCamera>>,
+    mut tile_query: Query<(&mut Tile, &TileLod, &mut Mesh3d, &Aabb, &GlobalTransform)>,
+) {
+    let Ok(camera_transform) = camera_query.single() else {
+        return;
+    };
+    let eye = camera_transform.translation();
+
+    for (mut tile, lod, mut mesh, aabb, transform) in tile_query.iter_mut() {
+        let center = transform.transform_point(aabb.center.into());
+        let simplified = terrain_manager.simplify_distant_chunks
+            && eye.distance(center) > SIMPLIFICATION_DISTANCE;
+
+        let (handle, triangle_count) = if simplified {
+            (&lod.simplified, lod.simplified_triangle_count)
+        } else {
+            (&lod.full, tile.full_triangle_count)
+        };
+
+        if mesh.0 != *handle {
+            mesh.0 = handle.clone();
+            tile.triangle_count = triangle_count;
+        }
+    }
+}
+
 const TREE_DENSITY: f32 = 0.6;
 const SNOW_DENSITY: f32 = 0.3;
 
-pub fn generate_terrain_mesh() -> (Mesh, Vec<[f32; 3]>, Vec<[f32; 3]>) {
-    let resolution = 5000;
-    let vertex_count = (resolution + 1) * (resolution + 1);
+/// Number of grid rows handled by one task when building the normal lines.
+const NORMAL_LINE_ROWS_PER_TASK: usize = 64;
 
-    let mut positions: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
-    let mut normals: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
-    let mut colors: Vec<[f32; 4]> = Vec::with_capacity(vertex_count);
-
-    for row in 0..=resolution {
-        for col in 0..=resolution {
-            let x = row as f32 - resolution as f32 / 2.0;
-            let z = col as f32 - resolution as f32 / 2.0;
-            let (y, normal) = sample(x, z);
-            positions.push([x, y, z]);
-            normals.push([normal.x, normal.y, normal.z]);
+/// Generates the terrain on the [`AsyncComputeTaskPool`], spreading the chunks across its threads.
+pub fn generate_terrain_mesh(
+    settings: &TerrainSettings,
+    cache: Option<&ChunkCache>,
+    progress: &GenerationProgress,
+) -> GeneratedTerrain {
+    let pool = AsyncComputeTaskPool::get_or_init(TaskPool::default);
+    generate_terrain_mesh_on(Some(pool), settings, cache, progress)
+}
+
+/// Generates the terrain on `pool`, or on the calling thread alone when `pool` is `None`.
+pub fn generate_terrain_mesh_on(
+    pool: Option<&TaskPool>,
+    settings: &TerrainSettings,
+    cache: Option<&ChunkCache>,
+    progress: &GenerationProgress,
+) -> GeneratedTerrain {
+    let resolution = settings.resolution;
+    assert!(resolution > 0, "terrain resolution must be positive");
+
+    let stride = resolution + 1;
+    let vertex_count = stride * stride;
+
+    let chunks_per_side = resolution.div_ceil(CHUNK_SIZE);
+    let chunk_count = chunks_per_side * chunks_per_side;
 
-            let mut color = ROCK_COLOR;
+    let chunk_coord = |index: usize| {
+        IVec2::new(
+            (index / chunks_per_side) as i32,
+            (index % chunks_per_side) as i32,
+        )
+    };
+    let chunk_rows = |coord: IVec2| chunk_size(settings, coord).x as usize + 1;
 
-            let (snow_density, _) = fbm(Vec2::new((x + 163.123) / 100.0, (z + 531.756) / 100.0));
-            let snow_density = snow_density * smoothstep_bounds(300.0, 500.0, y);
+    let sampled_rows = (0..chunk_count).map(|index| chunk_rows(chunk_coord(index))).sum();
+    progress.start_stage(GenerationStage::Sampling, sampled_rows);
 
-            if snow_density > SNOW_DENSITY {
-                let snow_blend = smoothstep_bounds(0.6, 0.65, normal.y);
-                color = ROCK_COLOR.mix(&Color::WHITE, snow_blend);
+    // Results come back in spawn order, i.e. row-major by chunk coordinate.
+    let chunk_data: Vec<(ChunkData, bool)> = run_tasks(
+        pool,
+        (0..chunk_count).map(|index| {
+            let coord = chunk_coord(index);
+            move || {
+                let result = load_or_generate_chunk(settings, cache, coord);
+                progress.advance(chunk_rows(coord));
+                result
             }
+        }),
+    );
+
+    if cache.is_some() {
+        let cached_chunks = chunk_data.iter().filter(|(_, cached)| *cached).count();
+        info!(
+            "Loaded {} of {} terrain chunks from cache",
+            cached_chunks, chunk_count
+        );
+    }
 
-            let (tree_density, _) = fbm(Vec2::new((x + 23.543) / 50.0, (z + 543.123) / 50.0));
-            let tree_density = tree_density * (1.0 - smoothstep_bounds(320.0, 450.0, y));
-            if tree_density > TREE_DENSITY {
-                let tree_blend = smoothstep_bounds(0.6, 0.75, normal.y);
-                color = color.mix(&TREE_COLOR, tree_blend)
+    let mut positions: Vec<[f32; 3]> = vec![[0.0; 3]; vertex_count];
+    let mut normals: Vec<[f32; 3]> = vec![[0.0; 3]; vertex_count];
+    let mut splat: Vec<[f32; 2]> = vec![[0.0; 2]; vertex_count];
+
+    for (index, (data, _)) in chunk_data.iter().enumerate() {
+        let coord = IVec2::new(
+            (index / chunks_per_side) as i32,
+            (index % chunks_per_side) as i32,
+        );
+        let origin = chunk_origin(settings, coord).as_vec3();
+        let size = chunk_size(settings, coord);
+        let first_row = coord.x as usize * CHUNK_SIZE;
+        let first_col = coord.y as usize * CHUNK_SIZE;
+        for row in 0..=size.x as usize {
+            for col in 0..=size.y as usize {
+                let local = row * (CHUNK_SIZE + 1) + col;
+                let global = (first_row + row) * stride + first_col + col;
+                positions[global] = [
+                    origin.x + row as f32,
+                    data.heights[local],
+                    origin.z + col as f32,
+                ];
+                normals[global] = data.normals[local];
+                splat[global] = data.splat[local];
+            }
+        }
+    }
+
+    progress.start_stage(GenerationStage::BuildingIndices, chunk_count);
+    let chunks: Vec<TerrainChunk> = run_tasks(
+        pool,
+        chunk_data.into_iter().enumerate().map(|(index, (data, _))| {
+            let coord = IVec2::new(
+                (index / chunks_per_side) as i32,
+                (index % chunks_per_side) as i32,
+            );
+            move || {
+                let chunk = build_chunk(
+                    chunk_origin(settings, coord),
+                    chunk_size(settings, coord),
+                    &data,
+                );
+                progress.advance(1);
+                chunk
             }
+        }),
+    );
+
+    let full_triangles: usize = chunks.iter().map(|chunk| chunk.triangle_count).sum();
+    let simplified_triangles: usize = chunks
+        .iter()
+        .map(|chunk| chunk.simplified_triangle_count)
+        .sum();
+    info!(
+        "Simplified terrain to {} of {} triangles ({:.1}% saved at {} m max error)",
+        simplified_triangles,
+        full_triangles,
+        100.0 * (1.0 - simplified_triangles as f32 / full_triangles as f32),
+        SIMPLIFIED_MAX_ERROR
+    );
+
+    let occluders = HorizonOccluders::from_positions(&positions, resolution);
+    let heightfield = TerrainHeightfield::new(
+        chunk_origin(settings, IVec2::ZERO).xz(),
+        resolution,
+        positions.iter().map(|position| position[1]).collect(),
+    );
+
+    GeneratedTerrain {
+        chunks,
+        occluders,
+        heightfield,
+        normals,
+        splat,
+    }
+}
+
+/// Runs `tasks` on `pool`, or one after another on the calling thread when `pool` is `None`, and
+/// returns their results in order.
+fn run_tasks<'env, T, F>(pool: Option<&TaskPool>, tasks: impl IntoIterator<Item = F>) -> Vec<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'env,
+{
+    match pool {
+        Some(pool) => pool.scope(|scope| {
+            for task in tasks {
+                scope.spawn(async move { task() });
+            }
+        }),
+        None => tasks.into_iter().map(|task| task()).collect(),
+    }
+}
+
+/// Returns the data of a chunk and whether it came from the cache.
+fn load_or_generate_chunk(
+    settings: &TerrainSettings,
+    cache: Option<&ChunkCache>,
+    coord: IVec2,
+) -> (ChunkData, bool) {
+    if let Some(data) = cache.and_then(|cache| cache.load(coord)) {
+        return (data, true);
+    }
+
+    let data = generate_chunk_data(settings, coord);
+    if let Some(cache) = cache
+        && let Err(error) = cache.store(coord, &data)
+    {
+        warn!("Failed to cache terrain chunk {coord}: {error}");
+    }
 
-            let color = color.to_linear();
+    (data, false)
+}
+
+/// World position of the first vertex of a chunk.
+fn chunk_origin(settings: &TerrainSettings, coord: IVec2) -> DVec3 {
+    let half = settings.resolution as f64 / 2.0;
+    DVec3::new(
+        (coord.x as i64 * CHUNK_SIZE as i64) as f64 - half,
+        0.0,
+        (coord.y as i64 * CHUNK_SIZE as i64) as f64 - half,
+    )
+}
+
+/// Number of grid quads of a chunk along x and z.
+///
+/// Chunk data always covers a whole chunk, sampling past the edge of the terrain for the last row
+/// and column of chunks, so that it can be simplified like any other chunk.
+fn chunk_size(settings: &TerrainSettings, coord: IVec2) -> UVec2 {
+    let remaining = |index: i32| {
+        (settings.resolution - index as usize * CHUNK_SIZE).min(CHUNK_SIZE) as u32
+    };
+    UVec2::new(remaining(coord.x), remaining(coord.y))
+}
+
+/// Whether any chunk of a terrain reaches past [`DOUBLE_PRECISION_DISTANCE`].
+///
+/// The precision is chosen once for the whole terrain rather than per chunk, so that neighbouring
+/// chunks compute the vertices on their shared border the same way and the meshes meet exactly.
+fn uses_double_precision(settings: &TerrainSettings) -> bool {
+    let last = settings.resolution.div_ceil(CHUNK_SIZE).saturating_sub(1) as i32;
+    // The terrain is square, and the chunk data of the last chunks runs a full chunk past their
+    // origin.
+    let first_vertex = chunk_origin(settings, IVec2::ZERO).x;
+    let last_vertex = chunk_origin(settings, IVec2::splat(last)).x + CHUNK_SIZE as f64;
+    first_vertex.abs().max(last_vertex.abs()) > DOUBLE_PRECISION_DISTANCE
+}
+
+pub fn generate_chunk_data(settings: &TerrainSettings, coord: IVec2) -> ChunkData {
+    let origin = chunk_origin(settings, coord);
 
-            colors.push([color.red, color.green, color.blue, color.alpha]);
+    if uses_double_precision(settings) {
+        return generate_chunk_data_f64(settings, origin);
+    }
+    let origin = origin.as_vec3();
+
+    let vertex_count = (CHUNK_SIZE + 1) * (CHUNK_SIZE + 1);
+    let mut heights: Vec<f32> = Vec::with_capacity(vertex_count);
+    let mut normals: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
+    let mut splat: Vec<[f32; 2]> = Vec::with_capacity(vertex_count);
+
+    for row in 0..=CHUNK_SIZE {
+        let x = origin.x + row as f32;
+        let mut col = 0;
+
+        // Whole batches of lanes first, then the remainder of the row one point at a time.
+        while col + LANES <= CHUNK_SIZE + 1 {
+            let xs = [x; LANES];
+            let zs: Lanes = std::array::from_fn(|lane| origin.z + (col + lane) as f32);
+
+            let (ys, lane_normals) = sample_lanes(settings, &xs, &zs);
+            let lane_splat = splat_weights_lanes(settings.seed, &xs, &zs, &ys, &lane_normals);
+
+            heights.extend(ys);
+            normals.extend(lane_normals.map(|normal| [normal.x, normal.y, normal.z]));
+            splat.extend(lane_splat);
+
+            col += LANES;
         }
+
+        for col in col..=CHUNK_SIZE {
+            let z = origin.z + col as f32;
+            let (y, normal) = sample(settings, x, z);
+            heights.push(y);
+            normals.push([normal.x, normal.y, normal.z]);
+            splat.push(splat_weights(settings.seed, x, z, y, normal));
+        }
+    }
+
+    ChunkData {
+        heights,
+        normals,
+        splat,
+    }
+}
+
+/// Samples a chunk of a terrain reaching far from the world origin, where `f32` coordinates are too
+/// coarse.
+fn generate_chunk_data_f64(settings: &TerrainSettings, origin: DVec3) -> ChunkData {
+    let vertex_count = (CHUNK_SIZE + 1) * (CHUNK_SIZE + 1);
+    let mut heights: Vec<f32> = Vec::with_capacity(vertex_count);
+    let mut normals: Vec<[f32; 3]> = Vec::with_capacity(vertex_count);
+    let mut splat: Vec<[f32; 2]> = Vec::with_capacity(vertex_count);
+
+    for row in 0..=CHUNK_SIZE {
+        for col in 0..=CHUNK_SIZE {
+            let x = origin.x + row as f64;
+            let z = origin.z + col as f64;
+            let (y, normal) = sample_f64(settings, x, z);
+            heights.push(y);
+            normals.push([normal.x, normal.y, normal.z]);
+            splat.push(splat_weights_f64(settings.seed, x, z, y, normal));
+        }
+    }
+
+    ChunkData {
+        heights,
+        normals,
+        splat,
+    }
+}
+
+fn snow_noise_point(x: f32, z: f32) -> Vec2 {
+    Vec2::new((x + 163.123) / 100.0, (z + 531.756) / 100.0)
+}
+
+fn tree_noise_point(x: f32, z: f32) -> Vec2 {
+    Vec2::new((x + 23.543) / 50.0, (z + 543.123) / 50.0)
+}
+
+/// Blend weights of the snow and tree cover at a terrain point.
+fn splat_weights(seed: u32, x: f32, z: f32, y: f32, normal: Vec3) -> [f32; 2] {
+    let (snow_noise, _) = fbm(snow_noise_point(x, z), seed);
+    let (tree_noise, _) = fbm(tree_noise_point(x, z), seed);
+
+    splat_blend(y, normal, snow_noise, tree_noise)
+}
+
+/// [`splat_weights`] with the noise evaluated in double precision.
+pub fn splat_weights_f64(seed: u32, x: f64, z: f64, y: f32, normal: Vec3) -> [f32; 2] {
+    let snow_point = DVec2::new((x + 163.123) / 100.0, (z + 531.756) / 100.0);
+    let tree_point = DVec2::new((x + 23.543) / 50.0, (z + 543.123) / 50.0);
+
+    let (snow_noise, _) = fbm_f64(snow_point, seed);
+    let (tree_noise, _) = fbm_f64(tree_point, seed);
+
+    splat_blend(y, normal, snow_noise as f32, tree_noise as f32)
+}
+
+/// [`splat_weights`] for [`LANES`] points at once.
+fn splat_weights_lanes(
+    seed: u32,
+    x: &Lanes,
+    z: &Lanes,
+    y: &Lanes,
+    normals: &[Vec3; LANES],
+) -> [[f32; 2]; LANES] {
+    let snow_points: [Vec2; LANES] = std::array::from_fn(|i| snow_noise_point(x[i], z[i]));
+    let tree_points: [Vec2; LANES] = std::array::from_fn(|i| tree_noise_point(x[i], z[i]));
+
+    let (snow_noise, _, _) = fbm_lanes(
+        &snow_points.map(|point| point.x),
+        &snow_points.map(|point| point.y),
+        seed,
+    );
+    let (tree_noise, _, _) = fbm_lanes(
+        &tree_points.map(|point| point.x),
+        &tree_points.map(|point| point.y),
+        seed,
+    );
+
+    std::array::from_fn(|i| splat_blend(y[i], normals[i], snow_noise[i], tree_noise[i]))
+}
+
+fn splat_blend(y: f32, normal: Vec3, snow_noise: f32, tree_noise: f32) -> [f32; 2] {
+    let mut snow_blend = 0.0;
+    let snow_density = snow_noise * smoothstep_bounds(300.0, 500.0, y);
+    if snow_density > SNOW_DENSITY {
+        snow_blend = smoothstep_bounds(0.6, 0.65, normal.y);
+    }
+
+    let mut tree_blend = 0.0;
+    let tree_density = tree_noise * (1.0 - smoothstep_bounds(320.0, 450.0, y));
+    if tree_density > TREE_DENSITY {
+        tree_blend = smoothstep_bounds(0.6, 0.75, normal.y);
     }
 
-    let mut indices: Vec<u32> = Vec::with_capacity(resolution * resolution * 6);
-    for row in 0..resolution {
-        for col in 0..resolution {
-            let top_left = (row * (resolution + 1) + col) as u32;
+    [snow_blend, tree_blend]
+}
+
+fn splat_color([snow_blend, tree_blend]: [f32; 2]) -> [f32; 4] {
+    let color = ROCK_COLOR
+        .mix(&Color::WHITE, snow_blend)
+        .mix(&TREE_COLOR, tree_blend)
+        .to_linear();
+
+    [color.red, color.green, color.blue, color.alpha]
+}
+
+fn build_chunk(origin: DVec3, size: UVec2, data: &ChunkData) -> TerrainChunk {
+    let grid_size = CHUNK_SIZE + 1;
+    let (rows, cols) = (size.x as usize, size.y as usize);
+
+    let chunk_positions: Vec<[f32; 3]> = (0..grid_size * grid_size)
+        .map(|index| {
+            let (row, col) = (index / grid_size, index % grid_size);
+            [row as f32, data.heights[index], col as f32]
+        })
+        .collect();
+    let chunk_colors: Vec<[f32; 4]> = data.splat.iter().copied().map(splat_color).collect();
+
+    // Grid indices of the vertices inside the chunk, which is all of them except at the far
+    // edges of the terrain.
+    let inside: Vec<usize> = (0..=rows)
+        .flat_map(|row| (0..=cols).map(move |col| row * grid_size + col))
+        .collect();
+
+    let min_height = inside
+        .iter()
+        .map(|&index| data.heights[index])
+        .fold(f32::MAX, f32::min);
+    let max_height = inside
+        .iter()
+        .map(|&index| data.heights[index])
+        .fold(f32::MIN, f32::max);
+
+    let (used_vertices, simplified_indices) = Rtin::new(&data.heights, grid_size)
+        .triangulate_chunk(SIMPLIFIED_MAX_ERROR, cols, rows);
+    let simplified_triangle_count = simplified_indices.len() / 3;
+
+    let simplified_mesh = build_mesh(
+        used_vertices
+            .iter()
+            .map(|&index| chunk_positions[index as usize])
+            .collect(),
+        used_vertices
+            .iter()
+            .map(|&index| data.normals[index as usize])
+            .collect(),
+        used_vertices
+            .iter()
+            .map(|&index| chunk_colors[index as usize])
+            .collect(),
+        simplified_indices,
+    );
+
+    let indices = grid_indices(rows, cols);
+    let triangle_count = indices.len() / 3;
+    let mesh = build_mesh(
+        inside.iter().map(|&index| chunk_positions[index]).collect(),
+        inside.iter().map(|&index| data.normals[index]).collect(),
+        inside.iter().map(|&index| chunk_colors[index]).collect(),
+        indices,
+    );
+
+    TerrainChunk {
+        origin,
+        size,
+        mesh,
+        simplified_mesh,
+        min_height,
+        max_height,
+        triangle_count,
+        simplified_triangle_count,
+    }
+}
+
+fn build_mesh(
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+) -> Mesh {
+    let mut mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    );
+
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    mesh.insert_indices(Indices::U32(indices));
+
+    mesh
+}
+
+/// Triangle indices for a row-major grid of `rows` x `cols` quads.
+fn grid_indices(rows: usize, cols: usize) -> Vec<u32> {
+    let mut indices: Vec<u32> = Vec::with_capacity(rows * cols * 6);
+    for row in 0..rows {
+        for col in 0..cols {
+            let top_left = (row * (cols + 1) + col) as u32;
             let top_right = top_left + 1;
-            let bottom_left = ((row + 1) * (resolution + 1) + col) as u32;
+            let bottom_left = ((row + 1) * (cols + 1) + col) as u32;
             let bottom_right = bottom_left + 1;
 
             // Two triangles per quad - clockwise winding for outward-facing triangles
@@ -130,69 +813,127 @@ pub fn generate_terrain_mesh() -> (Mesh, Vec<[f32; 3]>, Vec<[f32; 3]>) {
         }
     }
 
-    let mut mesh = Mesh::new(
-        PrimitiveTopology::TriangleList,
-        RenderAssetUsages::default(),
-    );
-
-    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
-    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals.clone());
-    mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
-    mesh.insert_indices(Indices::U32(indices));
-
-    (mesh, positions, normals)
+    indices
 }
 
-pub fn check_terrain_generation(
+fn check_terrain_generation(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut task_query: Query<(Entity, &mut TerrainGenerationTask)>,
+    progress: Option<Res<TerrainGenerationProgress>>,
+    world_origin: Res<WorldOrigin>,
+) {
+    if let Ok((entity, mut task)) = task_query.single_mut()
+        && let Some(progress) = progress
+        && let Some(result) = future::block_on(future::poll_once(&mut task.0))
+    {
+        let (
+            GeneratedTerrain {
+                chunks,
+                occluders,
+                heightfield,
+                ..
+            },
+            normal_lines,
+        ) = result;
+
+        let material = materials.add(StandardMaterial {
+            base_color: Color::WHITE,
+            perceptual_roughness: 1.0,
+            metallic: 0.0,
+            ..default()
+        });
+
+        progress
+            .0
+            .start_stage(GenerationStage::Uploading, chunks.len());
+        commands.insert_resource(PendingChunks { chunks, material });
+        commands.insert_resource(occluders);
+        commands.insert_resource(heightfield);
+
+        spawn_normals(
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+            normal_lines,
+            &world_origin,
+        );
+
+        // Clean up the task entity
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Spawns the generated chunks over several frames so that the loading screen keeps updating.
+fn upload_terrain_chunks(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    pending: Option<ResMut<PendingChunks>>,
+    progress: Option<Res<TerrainGenerationProgress>>,
     mut terrain_manager: ResMut<TerrainManager>,
+    world_origin: Res<WorldOrigin>,
 ) {
-    if let Ok((entity, mut task)) = task_query.single_mut() {
-        if let Some(result) = future::block_on(future::poll_once(&mut task.0)) {
-            let (mesh, positions, normals) = result;
-
-            spawn_terrain_entity(
-                &mut commands,
-                &mut meshes,
-                &mut materials,
-                mesh,
-                &terrain_manager,
-            );
-            terrain_manager.loaded = true;
-
-            spawn_normals(
-                &mut commands,
-                &mut meshes,
-                &mut materials,
-                &positions,
-                &normals,
-            );
+    let (Some(mut pending), Some(progress)) = (pending, progress) else {
+        return;
+    };
 
-            // Clean up the task entity
-            commands.entity(entity).despawn();
-        }
+    for _ in 0..CHUNKS_UPLOADED_PER_FRAME {
+        let Some(chunk) = pending.chunks.pop() else {
+            break;
+        };
+
+        let material = pending.material.clone();
+        spawn_terrain_entity(
+            &mut commands,
+            &mut meshes,
+            material,
+            chunk,
+            &terrain_manager,
+            &world_origin,
+        );
+        progress.0.advance(1);
+    }
+
+    if pending.chunks.is_empty() {
+        commands.remove_resource::<PendingChunks>();
+        terrain_manager.loaded = true;
     }
 }
 
 pub fn spawn_terrain_entity(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    mesh: Mesh,
+    material: Handle<StandardMaterial>,
+    chunk: TerrainChunk,
     terrain_manager: &TerrainManager,
+    world_origin: &WorldOrigin,
 ) {
+    // Give the chunk its real vertical extent up front so that frustum culling does not depend on
+    // the bounds Bevy would otherwise derive once the mesh asset is ready.
+    let aabb = Aabb::from_min_max(
+        Vec3::new(0.0, chunk.min_height, 0.0),
+        Vec3::new(chunk.size.x as f32, chunk.max_height, chunk.size.y as f32),
+    );
+
+    let full = meshes.add(chunk.mesh);
+    let simplified = meshes.add(chunk.simplified_mesh);
+
     let mut tile = commands.spawn((
-        Tile {},
-        MeshMaterial3d(materials.add(StandardMaterial {
-            base_color: Color::WHITE,
-            perceptual_roughness: 1.0,
-            metallic: 0.0,
-            ..default()
-        })),
-        Mesh3d(meshes.add(mesh)),
+        Tile {
+            origin: chunk.origin,
+            triangle_count: chunk.triangle_count,
+            full_triangle_count: chunk.triangle_count,
+        },
+        TileLod {
+            full: full.clone(),
+            simplified,
+            simplified_triangle_count: chunk.simplified_triangle_count,
+        },
+        MeshMaterial3d(material),
+        Mesh3d(full),
+        Transform::from_translation(world_origin.to_render(chunk.origin)),
+        aabb,
     ));
 
     if terrain_manager.wireframe_mode {
@@ -200,34 +941,72 @@ pub fn spawn_terrain_entity(
     }
 }
 
+/// Builds the line mesh of the normals debug view, one line along the normal of every grid vertex,
+/// on `pool` or on the calling thread alone when `pool` is `None`.
+///
+/// This is not part of [`generate_terrain_mesh_on`]: at full resolution the lines take several
+/// hundred megabytes, which only the interactive view needs.
+pub fn build_normal_lines(
+    pool: Option<&TaskPool>,
+    terrain: &GeneratedTerrain,
+    progress: &GenerationProgress,
+) -> Mesh {
+    let heightfield = &terrain.heightfield;
+    let stride = heightfield.resolution() + 1;
+    let origin = heightfield.origin().as_vec2();
+    progress.start_stage(GenerationStage::BuildingNormals, stride);
+
+    // Create normal visualization mesh
+    let normal_length = 1.0;
+    let batch_rows = NORMAL_LINE_ROWS_PER_TASK;
+    let mut line_positions: Vec<[f32; 3]> = vec![[0.0; 3]; terrain.normals.len() * 2];
+
+    run_tasks(
+        pool,
+        line_positions
+            .chunks_mut(batch_rows * stride * 2)
+            .zip(heightfield.heights().chunks(batch_rows * stride))
+            .zip(terrain.normals.chunks(batch_rows * stride))
+            .enumerate()
+            .map(|(batch, ((batch_lines, batch_heights), batch_normals))| {
+                move || {
+                    let first_row = batch * batch_rows;
+                    for (index, ((line, height), normal)) in batch_lines
+                        .chunks_exact_mut(2)
+                        .zip(batch_heights)
+                        .zip(batch_normals)
+                        .enumerate()
+                    {
+                        let (row, col) = (first_row + index / stride, index % stride);
+                        let pos = [origin.x + row as f32, *height, origin.y + col as f32];
+                        line[0] = pos;
+                        line[1] = [
+                            pos[0] + normal[0] * normal_length,
+                            pos[1] + normal[1] * normal_length,
+                            pos[2] + normal[2] * normal_length,
+                        ];
+                    }
+                    progress.advance(batch_heights.len() / stride);
+                }
+            }),
+    );
+
+    Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, line_positions)
+}
+
 fn spawn_normals(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
-    positions: &Vec<[f32; 3]>,
-    normals: &Vec<[f32; 3]>,
+    normal_lines: Mesh,
+    world_origin: &WorldOrigin,
 ) {
-    // Create normal visualization mesh
-    let normal_length = 1.0;
-    let mut line_positions: Vec<[f32; 3]> = Vec::new();
-
-    for (pos, normal) in positions.iter().zip(normals.iter()) {
-        line_positions.push(*pos);
-        line_positions.push([
-            pos[0] + normal[0] * normal_length,
-            pos[1] + normal[1] * normal_length,
-            pos[2] + normal[2] * normal_length,
-        ]);
-    }
-
+    // The normal lines are built in world space
     commands.spawn((
         NormalLines,
-        Mesh3d(
-            meshes.add(
-                Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
-                    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, line_positions.clone()),
-            ),
-        ),
+        Mesh3d(meshes.add(normal_lines)),
+        Transform::from_translation(world_origin.to_render(DVec3::ZERO)),
         MeshMaterial3d(materials.add(StandardMaterial {
             base_color: Color::srgb(0.0, 1.0, 1.0),
             unlit: true,
@@ -237,11 +1016,11 @@ fn spawn_normals(
     ));
 }
 
-fn sample(x: f32, z: f32) -> (f32, Vec3) {
-    let amplitude = 300.0;
-    let scale = 800.0;
+fn sample(settings: &TerrainSettings, x: f32, z: f32) -> (f32, Vec3) {
+    let amplitude = settings.amplitude;
+    let scale = settings.scale;
 
-    let (y, d) = fbm(Vec2::new(x, z) / scale);
+    let (y, d) = fbm(Vec2::new(x, z) / scale, settings.seed);
 
     let adjusted_y = y * amplitude;
     let adjusted_d = d * amplitude / scale;
@@ -252,86 +1031,270 @@ fn sample(x: f32, z: f32) -> (f32, Vec3) {
     )
 }
 
-fn hash(p: Vec2) -> f32 {
-    // Convert to signed integers (floored)
-    let ix = p.x.floor() as i32;
-    let iy = p.y.floor() as i32;
+/// [`sample`] evaluated in double precision, for points far from the world origin.
+pub fn sample_f64(settings: &TerrainSettings, x: f64, z: f64) -> (f32, Vec3) {
+    let amplitude = settings.amplitude as f64;
+    let scale = settings.scale as f64;
 
-    let seed = 3266489917_u32;
-    let mut h = seed
-        .wrapping_add((ix as u32).wrapping_mul(374761393))
-        .wrapping_add((iy as u32).wrapping_mul(668265263));
+    let (y, d) = fbm_f64(DVec2::new(x, z) / scale, settings.seed);
 
-    // Avalanche step (bit diffusion)
-    h ^= h >> 13;
-    h = h.wrapping_mul(1274126177);
+    let adjusted_y = y * amplitude;
+    let adjusted_d = d * amplitude / scale;
 
-    // Convert to float in [0, 1)
-    (h as f32) * (1.0 / 4294967296.0)
+    (
+        adjusted_y as f32,
+        DVec3::new(-adjusted_d.x, 1.0, -adjusted_d.y)
+            .normalize()
+            .as_vec3(),
+    )
 }
 
-fn smoothstep(x: f32) -> f32 {
-    let x = x.clamp(0.0, 1.0);
-    x * x * (3.0 - 2.0 * x)
-}
+/// [`sample`] for [`LANES`] points at once, with bit-identical results.
+fn sample_lanes(settings: &TerrainSettings, x: &Lanes, z: &Lanes) -> (Lanes, [Vec3; LANES]) {
+    let amplitude = settings.amplitude;
+    let scale = settings.scale;
 
-fn smoothstep_bounds(low: f32, high: f32, x: f32) -> f32 {
-    let t = ((x - low) / (high - low)).clamp(0.0, 1.0);
-    smoothstep(t)
+    let (y, dx, dz) = fbm_lanes(&x.map(|x| x / scale), &z.map(|z| z / scale), settings.seed);
+
+    let heights = y.map(|y| y * amplitude);
+    let normals = std::array::from_fn(|i| {
+        let adjusted_dx = dx[i] * amplitude / scale;
+        let adjusted_dz = dz[i] * amplitude / scale;
+        Vec3::new(-adjusted_dx, 1.0, -adjusted_dz).normalize()
+    });
+
+    (heights, normals)
 }
 
-fn noise(t: Vec2) -> (f32, Vec2) // value, dx, dy
-{
-    let p = t.floor();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FNV-1a over the little-endian bytes of the words.
+    fn checksum(words: impl IntoIterator<Item = u32>) -> u64 {
+        words.into_iter().fold(0xcbf29ce484222325, |hash, word| {
+            word.to_le_bytes()
+                .into_iter()
+                .fold(hash, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+        })
+    }
 
-    let a = hash(p + Vec2::new(0.0, 0.0));
-    let b = hash(p + Vec2::new(1.0, 0.0));
-    let c = hash(p + Vec2::new(0.0, 1.0));
-    let d = hash(p + Vec2::new(1.0, 1.0));
+    /// Bit patterns of the positions, normals and colours of a mesh, followed by its indices.
+    fn mesh_words(mesh: &Mesh) -> Vec<u32> {
+        let mut words: Vec<u32> = [
+            Mesh::ATTRIBUTE_POSITION,
+            Mesh::ATTRIBUTE_NORMAL,
+            Mesh::ATTRIBUTE_COLOR,
+        ]
+        .into_iter()
+        .flat_map(|attribute| {
+            let values = mesh.attribute(attribute).unwrap();
+            values
+                .get_bytes()
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
 
-    let k0 = a;
-    let k1 = b - a;
-    let k2 = c - a;
-    let k3 = a - b - c + d;
+        words.extend(mesh.indices().unwrap().iter().map(|index| index as u32));
+        words
+    }
 
-    let w = t.fract_gl();
-    let (sx, sz) = (smoothstep(w.x), smoothstep(w.y));
+    // Golden values: any change to these means every generated world changes too.
 
-    let value = k0 + k1 * sx + k2 * sz + k3 * sx * sz;
+    #[test]
+    fn sample_known_values() {
+        let settings = TerrainSettings::default();
 
-    let ds = 6.0 * w * (1.0 - w);
-    let dx = ds.x * (k1 + k3 * sz);
-    let dy = ds.y * (k2 + k3 * sx);
+        assert_eq!(sample(&settings, 0.0, 0.0), (543.9278, Vec3::Y));
+        assert_eq!(
+            sample(&settings, 123.5, -456.25),
+            (364.43793, Vec3::new(-0.13649282, 0.9597605, -0.24541698))
+        );
+        assert_eq!(
+            sample(&settings, -2000.0, 1750.0),
+            (307.96786, Vec3::new(-0.20417598, 0.97878623, 0.01702154))
+        );
+    }
 
-    (value, Vec2::new(dx, dy))
-}
+    /// Normal of the terrain at a world point, from central differences of the height in double
+    /// precision.
+    fn finite_difference_normal(settings: &TerrainSettings, x: f64, z: f64) -> DVec3 {
+        let step = 1e-4;
+        let scale = settings.scale as f64;
+        let height = |x: f64, z: f64| {
+            fbm_f64(DVec2::new(x, z) / scale, settings.seed).0 * settings.amplitude as f64
+        };
 
-const ROTATION: Mat2 = Mat2::from_cols_array(&[0.8, 0.6, -0.6, 0.8]);
-const ROTATION_TRANSPOSE: Mat2 = Mat2::from_cols_array(&[0.8, -0.6, 0.6, 0.8]);
+        let dx = (height(x + step, z) - height(x - step, z)) / (2.0 * step);
+        let dz = (height(x, z + step) - height(x, z - step)) / (2.0 * step);
+        DVec3::new(-dx, 1.0, -dz).normalize()
+    }
 
-fn fbm(point: Vec2) -> (f32, Vec2) // value, dx, dy
-{
-    let scale_factor = 2.0;
+    #[test]
+    fn sample_normals_match_finite_differences() {
+        let settings = TerrainSettings::default();
 
-    let mut p = point;
-    let mut scale = 1.0;
+        // Points spread over the default terrain by the R2 low-discrepancy sequence.
+        let (a1, a2) = (0.7548776662466927, 0.5698402909980532);
+        for i in 0..200 {
+            let x = ((i as f64 * a1).fract() * 2.0 - 1.0) * 2500.0;
+            let z = ((i as f64 * a2).fract() * 2.0 - 1.0) * 2500.0;
+            let expected = finite_difference_normal(&settings, x, z);
 
-    let mut rotation = Mat2::IDENTITY;
+            let (_, single) = sample(&settings, x as f32, z as f32);
+            let (_, double) = sample_f64(&settings, x, z);
+            for (precision, normal, tolerance) in [("f32", single, 2e-3), ("f64", double, 1e-4)] {
+                let angle = normal.as_dvec3().angle_between(expected);
+                assert!(
+                    angle <= tolerance,
+                    "{precision} normal at ({x}, {z}) is {normal}, expected {expected}"
+                );
+            }
+        }
+    }
 
-    let mut value = 0.0;
-    let mut derivative = Vec2::new(0.0, 0.0);
+    #[test]
+    fn partial_chunks_stop_at_the_terrain_edge() {
+        let settings = TerrainSettings {
+            resolution: CHUNK_SIZE + 40,
+            ..default()
+        };
+        let terrain = generate_terrain_mesh(&settings, None, &GenerationProgress::default());
 
-    for _ in 0..11 {
-        let (noise, noise_derivative) = noise(p);
+        let sizes: Vec<UVec2> = terrain.chunks.iter().map(|chunk| chunk.size).collect();
+        assert_eq!(
+            sizes,
+            [UVec2::new(256, 256), UVec2::new(256, 40), UVec2::new(40, 256), UVec2::new(40, 40)]
+        );
 
-        value += scale * noise;
-        derivative += scale * rotation * noise_derivative;
+        for chunk in &terrain.chunks {
+            let (rows, cols) = (chunk.size.x as usize, chunk.size.y as usize);
+            assert_eq!(chunk.mesh.count_vertices(), (rows + 1) * (cols + 1));
+            assert_eq!(chunk.triangle_count, rows * cols * 2);
 
-        scale /= scale_factor;
+            for mesh in [&chunk.mesh, &chunk.simplified_mesh] {
+                let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+                for [x, _, z] in positions.as_float3().unwrap() {
+                    assert!(*x <= rows as f32 && *z <= cols as f32);
+                }
+            }
+        }
 
-        p = scale_factor * ROTATION * p;
-        rotation = scale_factor * ROTATION_TRANSPOSE * rotation;
+        // The last vertex of the terrain lies on its far corner.
+        let last = terrain.chunks.last().unwrap();
+        assert_eq!(last.origin + DVec3::new(40.0, 0.0, 40.0), DVec3::new(148.0, 0.0, 148.0));
+        assert_eq!(terrain.heightfield.heights().len(), (settings.resolution + 1).pow(2));
     }
 
-    (value, derivative)
+    #[test]
+    fn simplified_chunks_share_their_borders() {
+        let settings = TerrainSettings {
+            resolution: 2 * CHUNK_SIZE,
+            ..default()
+        };
+        let terrain = generate_terrain_mesh(&settings, None, &GenerationProgress::default());
+
+        // Every grid vertex along each edge of every chunk, as on the full-resolution meshes.
+        for chunk in &terrain.chunks {
+            let positions = chunk.simplified_mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap();
+            let positions = positions.as_float3().unwrap();
+            let edge_vertices = |on_edge: &dyn Fn(f32, f32) -> bool| {
+                positions.iter().filter(|[x, _, z]| on_edge(*x, *z)).count()
+            };
+
+            let size = CHUNK_SIZE as f32;
+            assert_eq!(edge_vertices(&|x, _| x == 0.0), CHUNK_SIZE + 1);
+            assert_eq!(edge_vertices(&|x, _| x == size), CHUNK_SIZE + 1);
+            assert_eq!(edge_vertices(&|_, z| z == 0.0), CHUNK_SIZE + 1);
+            assert_eq!(edge_vertices(&|_, z| z == size), CHUNK_SIZE + 1);
+        }
+    }
+
+    #[test]
+    fn chunks_share_their_borders_past_the_double_precision_distance() {
+        let settings = TerrainSettings {
+            resolution: 130 * CHUNK_SIZE,
+            ..default()
+        };
+        // The first of these chunks ends exactly at DOUBLE_PRECISION_DISTANCE and the second one
+        // starts there.
+        let (near, far) = (IVec2::new(128, 65), IVec2::new(129, 65));
+        assert_eq!(
+            chunk_origin(&settings, near).x + CHUNK_SIZE as f64,
+            DOUBLE_PRECISION_DISTANCE
+        );
+        assert_eq!(chunk_origin(&settings, far).x, DOUBLE_PRECISION_DISTANCE);
+
+        let near = generate_chunk_data(&settings, near);
+        let far = generate_chunk_data(&settings, far);
+        let last_row = CHUNK_SIZE * (CHUNK_SIZE + 1);
+        for col in 0..=CHUNK_SIZE {
+            let (near_index, far_index) = (last_row + col, col);
+            assert_eq!(
+                near.heights[near_index].to_bits(),
+                far.heights[far_index].to_bits()
+            );
+            assert_eq!(near.normals[near_index], far.normals[far_index]);
+            assert_eq!(near.splat[near_index], far.splat[far_index]);
+        }
+    }
+
+    #[test]
+    fn chunk_data_matches_generator_version() {
+        let settings = TerrainSettings::default();
+        let data = generate_chunk_data(&settings, IVec2::new(3, 7));
+        let words = data
+            .heights
+            .iter()
+            .chain(data.normals.iter().flatten())
+            .chain(data.splat.iter().flatten())
+            .map(|value| value.to_bits());
+
+        assert_eq!(
+            (GENERATOR_VERSION, checksum(words)),
+            (2, 0xe2978743fc4a2699),
+            "the generated chunk data changed: bump GENERATOR_VERSION and update the checksum"
+        );
+    }
+
+    #[test]
+    fn small_terrain_is_stable() {
+        let settings = TerrainSettings {
+            resolution: 2 * CHUNK_SIZE,
+            ..default()
+        };
+        let terrain = generate_terrain_mesh(&settings, None, &GenerationProgress::default());
+
+        assert_eq!(terrain.chunks.len(), 4);
+        for chunk in &terrain.chunks {
+            assert_eq!(chunk.mesh.count_vertices(), (CHUNK_SIZE + 1) * (CHUNK_SIZE + 1));
+            assert_eq!(chunk.mesh.indices().unwrap().len(), CHUNK_SIZE * CHUNK_SIZE * 6);
+            assert_eq!(chunk.triangle_count, CHUNK_SIZE * CHUNK_SIZE * 2);
+        }
+
+        let simplified_triangles: Vec<usize> = terrain
+            .chunks
+            .iter()
+            .map(|chunk| chunk.simplified_triangle_count)
+            .collect();
+        assert_eq!(simplified_triangles, [16842, 16336, 16584, 16282]);
+
+        let mesh_checksum = checksum(terrain.chunks.iter().flat_map(|chunk| {
+            let mut words = mesh_words(&chunk.mesh);
+            words.extend(mesh_words(&chunk.simplified_mesh));
+            words
+        }));
+        let height_checksum = checksum(
+            terrain
+                .heightfield
+                .heights()
+                .iter()
+                .map(|height| height.to_bits()),
+        );
+
+        assert_eq!(mesh_checksum, 0x180393db2e0ad9ce);
+        assert_eq!(height_checksum, 0x99f55f09bf3c4bd6);
+    }
 }